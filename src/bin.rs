@@ -5,26 +5,183 @@ extern crate rustine;
 
 use chrono::prelude::*;
 use notify::{RecursiveMode, Watcher};
+use rustine::cli;
+use rustine::cli::Command;
 use rustine::config;
 use rustine::config::AlarmConfig;
+use rustine::config::edit;
 use rustine::input;
 use rustine::input::Message;
+use rustine::paths;
+use rustine::pomodoro;
+use rustine::pomodoro::{Action, Pomodoro};
 use rustine::runner;
+use rustine::runner::Sound;
+use rustine::scheduler;
+use rustine::scheduler::Scheduler;
+use rustine::state::State;
+use rustine::timer;
+use rustine::timer::{Timer, Timers};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
 
+/// Prints the alarms with their position, used to refer to them in commands
+fn print_alarms(scheduler: &Scheduler, now: &DateTime<Local>) {
+    if let Some(banner) = cli::pause_banner(&scheduler.alarms, now) {
+        println!("{}", banner);
+    }
+    for (index, alarm) in scheduler.alarms.iter().enumerate() {
+        print!("{}. ", index + 1);
+        alarm.pretty_print(now)
+    }
+
+    let disabled = scheduler.alarms.iter().filter(|alarm| alarm.disabled).count();
+    if disabled > 0 {
+        println!("{} of {} alarms disabled, 'enable <alarm>' to run them again", disabled, scheduler.alarms.len());
+    }
+}
+
+/// Adds, changes, removes, enables or disables an alarm of the configuration files, or pauses the alarms
+/// Returns the alarms read again with a description of the change
+fn change_configuration(message: &Message, config_file: &Path, alarms: &[AlarmConfig]) -> Result<(Vec<AlarmConfig>, String), String> {
+    let find = |id: &str| scheduler::find(alarms, id).map(|index| (index, &alarms[index])).ok_or(format!("Unknown alarm: {}", id));
+
+    match *message {
+        Message::Add(ref line) => {
+            let (changed, index) = edit::add_alarm(config_file, line)?;
+            let done = format!("Alarm {} added at {}", index + 1, changed[index].origin);
+            Ok((changed, done))
+        },
+        Message::Edit(ref id, ref changes) => {
+            let (index, alarm) = find(id)?;
+            Ok((edit::edit_alarm(config_file, alarm, changes)?, format!("Alarm {} changed at {}", index + 1, alarm.origin)))
+        },
+        Message::Remove(ref id) => {
+            let (index, alarm) = find(id)?;
+            Ok((edit::remove_alarm(config_file, alarm)?, format!("Alarm {} removed from {}", index + 1, alarm.origin)))
+        },
+        Message::Enable(ref id) | Message::Disable(ref id) => {
+            let (index, alarm) = find(id)?;
+            let enabled = matches!(*message, Message::Enable(_));
+            let done = format!("Alarm {} {} at {}", index + 1, if enabled { "enabled" } else { "disabled" }, alarm.origin);
+            Ok((edit::set_enabled(config_file, alarm, enabled)?, done))
+        },
+        Message::CountReached(ref key) => {
+            let (index, alarm) = alarms.iter().enumerate().find(|(_, alarm)| alarm.key() == *key).ok_or(format!("Unknown alarm: {}", key))?;
+            let done = format!("Alarm {} reached its count of {} runs and is now disabled", index + 1, alarm.count.unwrap_or_default());
+            Ok((edit::set_enabled(config_file, alarm, false)?, done))
+        },
+        Message::Pause(ref until) => cli::change_pause(config_file, Some(until)),
+        Message::Resume => cli::change_pause(config_file, None),
+        _ => Err("Not a change of the configuration".to_string())
+    }
+}
+
+/// Skips the next run of an alarm, or moves it to the given time, and keeps the change in the state file
+fn change_next_run(scheduler: &mut Scheduler, state: &mut State, state_file: &Path, id: &str, time: Option<&str>) -> Result<String, String> {
+    let index = scheduler.find(id).ok_or(format!("Unknown alarm: {}", id))?;
+    let alarm = &mut scheduler.alarms[index];
+    let exception = alarm.change_next_run(&Local::now(), time)?;
+
+    let mut changed = state.clone();
+    changed.set_exception(&alarm.key(), Some(exception.clone()));
+    changed.save(state_file)?;
+    *state = changed;
+
+    let done = match exception.moved_to {
+        Some(ref moved_to) => format!("Alarm {} runs at {} instead of {}", index + 1, moved_to.to_rfc3339(), exception.run.to_rfc3339()),
+        None => format!("Alarm {} skips its run at {}", index + 1, exception.run.to_rfc3339())
+    };
+    alarm.exception = Some(exception);
+    Ok(done)
+}
+
+/// Keeps the runs of the started alarms that have a count of runs in the state file
+/// Alarms that reached their count stop running and the main thread is asked to disable them in the configuration file
+fn count_runs(scheduler: &Scheduler, started: &[usize], state: &mut State, state_file: &Path, tx_main: &mpsc::Sender<Message>) {
+    let counted: Vec<&AlarmConfig> = started.iter().map(|index| &scheduler.alarms[*index]).filter(|alarm| alarm.count.is_some()).collect();
+    for alarm in counted.iter() {
+        state.set_runs(&alarm.key(), alarm.runs);
+        if alarm.remaining_runs() == Some(0) {
+            let _send_result = tx_main.send(Message::CountReached(alarm.key()));
+        }
+    }
+    if !counted.is_empty() {
+        if let Err(err) = state.save(state_file) {
+            println!("{}", err);
+        }
+    }
+}
+
+/// Starts the counters of the alarms that reached their count again once the configuration file disables them,
+/// so that they run their count again when enabled
+/// Alarms that reached their count but are still enabled, as when the file could not be written, are disabled again
+fn restart_counters(scheduler: &mut Scheduler, state: &mut State, state_file: &Path, tx_main: &mpsc::Sender<Message>) {
+    let mut restarted = false;
+    for alarm in scheduler.alarms.iter_mut().filter(|alarm| alarm.remaining_runs() == Some(0)) {
+        if alarm.disabled {
+            state.set_runs(&alarm.key(), 0);
+            alarm.runs = 0;
+            restarted = true;
+        } else {
+            let _send_result = tx_main.send(Message::CountReached(alarm.key()));
+        }
+    }
+    if restarted {
+        if let Err(err) = state.save(state_file) {
+            println!("{}", err);
+        }
+    }
+}
+
+/// Rings the last timer that ended while an alarm was ringing, once the alarm is stopped or snoozed
+fn ring_waiting_timers(waiting_timers: &mut Vec<Timer>, tx_alarm_runner: &mpsc::Sender<Message>) {
+    if let Some(timer) = waiting_timers.last() {
+        println!("Timer {} rings", timer.name());
+        let _send_result = tx_alarm_runner.send(Message::Play(timer.sound()));
+    }
+    waiting_timers.clear();
+}
+
+/// Watches the directories of the configuration files that are outside of the watched `directory`
+fn watch_link_targets<W: Watcher>(watcher: &mut W, config_files: &[PathBuf], directory: &Path) {
+    for parent in config_files.iter().filter_map(|file| file.parent()).filter(|parent| !parent.starts_with(directory)) {
+        if let Err(err) = watcher.watch(parent, RecursiveMode::NonRecursive) {
+            println!("Unable to watch {}: {:?}", parent.display(), err);
+        }
+    }
+}
+
 /// This is the application entry point
-/// Configuration file is located at ./rustine_config/config
+/// The configuration file is given by `--config <file>`, see `paths::config_file` otherwise
+/// Commands other than `run` are executed by `cli::execute`
 fn main() {
 
     // timeout for channel message wait
     let channel_wait_timeout = Duration::from_secs(1);
 
+    let args: Vec<String> = env::args().skip(1).collect();
+    let arguments = cli::parse_arguments(&args).unwrap_or_else(|err| {
+        println!("{}\n{}", err, cli::USAGE);
+        process::exit(2);
+    });
+    let config_file = paths::config_file(arguments.config.as_deref());
+    if arguments.command != Command::Run {
+        process::exit(cli::execute(&arguments.command, &config_file, arguments.json));
+    }
+    println!("Configuration file: {}", config_file.display());
 
-    let path = String::from("./rustine_config");
-    let path2 = path.clone();
-    let config_file = path.clone() + "/config";
+    // events name the files with the directory as it is watched
+    let path = paths::directory_of(&config_file);
+    let mut watched_files = paths::configuration_files(&config_file, &path);
+    let conf_d = path.join("conf.d");
+    let state_file = paths::state_file(&config_file);
+    let config_directory = path.clone();
+    let config_file = config_file.to_string_lossy().into_owned();
 
     // Create a channel to receive the events from the configuration update notifier.
     let (tx_config_update, rx_config_update) = mpsc::channel();
@@ -32,6 +189,8 @@ fn main() {
     let (tx_alarm_manager, rx_alarm_manager) = mpsc::channel();
     // Create a channel to wait for user input.
     let (tx_keyboard_input, rx_keyboard_input) = mpsc::channel();
+    // The alarm manager sends the changes of the configuration files to the main thread, with the user input
+    let tx_main = tx_keyboard_input.clone();
     // Create a channel to run alarm notification
     let (tx_alarm_runner, rx_alarm_runner) = mpsc::channel();
 
@@ -41,16 +200,33 @@ fn main() {
     // Create a watcher object, delivering debounced events.
     // The notification back-end is selected based on the platform.
     let mut watcher = notify::watcher(tx_config_update, debounce_timeout).unwrap();
-    // Add a path to be watched. All files and directories at that path and
-    // below will be monitored for changes.
-    watcher.watch(path, RecursiveMode::Recursive).unwrap();
+    // Watch the directory of the configuration file, to follow editors that replace the file,
+    // recursively for its conf.d directory, even when it is created later,
+    // and the directory of the file it links to
+    if let Err(err) = watcher.watch(&path, RecursiveMode::Recursive) {
+        println!("Unable to watch {}, configuration changes will not be reloaded: {:?}", path.display(), err);
+        println!("Run `rustine-bin init` to create the configuration");
+    }
+    watch_link_targets(&mut watcher, &watched_files, &path);
 
 
     // Thread that checks when an alarm should be launched
     // Launches/ stops the alarms
     let alarm_manager = thread::spawn(move || {
 
-        let mut alarms: Vec<AlarmConfig> = vec![];
+        let mut scheduler = Scheduler::new(vec![]);
+        // position of the alarm that rang last, until it is stopped
+        let mut ringing: Option<usize> = None;
+        // alarms that ring again at the given time
+        let mut snoozed: Vec<(DateTime<Local>, usize)> = vec![];
+        // skipped and moved runs and counted runs, kept in the state file to survive restarts
+        let mut state = State::load(&state_file);
+        // one-off alarms started from the console
+        let mut timers = Timers::default();
+        // timers that ended while an alarm was ringing, they ring once it stops
+        let mut waiting_timers: Vec<Timer> = vec![];
+        // work and break phases started from the console
+        let mut running_pomodoro: Option<Pomodoro> = None;
 
         let runner = runner::start(rx_alarm_runner);
 
@@ -61,27 +237,160 @@ fn main() {
                 Err(mpsc::RecvTimeoutError::Timeout) => {},
                 // Update alarm configuration
                 Ok(Message::Reconfigure(new_config)) => {
-                    alarms = new_config;
+                    let positions = scheduler.reconfigure(new_config);
+                    state.apply(&mut scheduler.alarms);
+                    restart_counters(&mut scheduler, &mut state, &state_file, &tx_main);
+                    // the ringing alarm can still be stopped by label or snoozed, unless it was removed
+                    ringing = ringing.and_then(|index| positions[index]);
+                    // snoozed alarms still ring again, unless they were removed
+                    snoozed = snoozed.into_iter().filter_map(|(until, index)| positions[index].map(|position| (until, position))).collect();
 
                     println!("Configuration updated:");
-                    for alarm in alarms.iter() {
-                        alarm.pretty_print(&Local::now())
-                    }
+                    print_alarms(&scheduler, &Local::now());
                 },
                 // Show current configuration
                 Ok(Message::Show) => {
                     println!("Configuration:");
-                    for alarm in alarms.iter() {
-                        alarm.pretty_print(&Local::now())
+                    print_alarms(&scheduler, &Local::now());
+                },
+                // Show a single alarm
+                Ok(Message::ShowAlarm(id)) => {
+                    match scheduler.find(&id) {
+                        Some(index) => {
+                            print!("{}. ", index + 1);
+                            scheduler.alarms[index].pretty_print(&Local::now())
+                        },
+                        None => println!("Unknown alarm: {}", id)
+                    }
+                },
+                // Show the next alarm to run
+                Ok(Message::Next) => {
+                    let now = Local::now();
+                    match scheduler.next(&now) {
+                        Some((run, index)) => {
+                            println!("Next alarm: {}", run.to_rfc3339());
+                            scheduler.alarms[index].pretty_print(&now)
+                        },
+                        None => println!("No alarm in the coming week")
                     }
                 },
                 // Forward message to stop the running alarm
                 Ok(Message::StopAlarm) => {
+                    ringing = None;
                     let _send_result = tx_alarm_runner.send(Message::StopAlarm);
+                    ring_waiting_timers(&mut waiting_timers, &tx_alarm_runner);
+                },
+                // Forward message to stop the running alarm, when it is the given one
+                Ok(Message::StopNamedAlarm(id)) => {
+                    match scheduler.find(&id) {
+                        Some(index) if ringing == Some(index) => {
+                            ringing = None;
+                            let _send_result = tx_alarm_runner.send(Message::StopAlarm);
+                            ring_waiting_timers(&mut waiting_timers, &tx_alarm_runner);
+                        },
+                        Some(_) => println!("Alarm {} is not ringing", id),
+                        None => println!("Unknown alarm: {}", id)
+                    }
+                },
+                // Stop the running alarm and ring it again later
+                Ok(Message::Snooze) => {
+                    match ringing.take() {
+                        Some(index) => {
+                            let _send_result = tx_alarm_runner.send(Message::StopAlarm);
+                            let snooze = scheduler.alarms[index].snooze;
+                            if snooze == 0 {
+                                println!("Alarm {} can not be snoozed, stopped", index + 1);
+                            } else {
+                                let until = Local::now() + chrono::Duration::minutes(snooze as i64);
+                                println!("Alarm {} snoozed until {}", index + 1, until.format("%H:%M:%S"));
+                                snoozed.push((until, index));
+                            }
+                            ring_waiting_timers(&mut waiting_timers, &tx_alarm_runner);
+                        },
+                        None => println!("No alarm is ringing")
+                    }
+                },
+                // Skip or move the next run of an alarm
+                Ok(Message::Skip(id)) => {
+                    match change_next_run(&mut scheduler, &mut state, &state_file, &id, None) {
+                        Ok(done) => println!("{}", done),
+                        Err(err) => println!("Next run not changed: {}", err)
+                    }
+                },
+                Ok(Message::Override(id, time)) => {
+                    match change_next_run(&mut scheduler, &mut state, &state_file, &id, Some(&time)) {
+                        Ok(done) => println!("{}", done),
+                        Err(err) => println!("Next run not changed: {}", err)
+                    }
+                },
+                // Start a timer, after checking that its audio file plays
+                Ok(Message::Timer(argument)) => {
+                    let now = Local::now();
+                    let started = timer::parse_timer(&argument, &config_directory).and_then(|(duration, label, audio)| {
+                        let audio = audio.unwrap_or_else(|| timer::default_audio(&config_directory));
+                        runner::check_audio(&audio)?;
+                        timers.start(duration, label, audio, &now).cloned()
+                    });
+                    match started {
+                        Ok(timer) => println!("Timer {} rings at {}", timer.name(), timer.end.format("%H:%M:%S")),
+                        Err(err) => println!("Timer not started: {}", err)
+                    }
+                },
+                // Show the running timers
+                Ok(Message::Timers) => {
+                    let now = Local::now();
+                    if timers.timers.is_empty() {
+                        println!("No timer running");
+                    }
+                    for timer in timers.timers.iter() {
+                        println!("{}. {} left, rings at {}{}", timer.id, timer::format_remaining(timer.end - now), timer.end.format("%H:%M:%S"),
+                            timer.label.as_ref().map(|label| format!(", {}", label)).unwrap_or_default());
+                    }
+                },
+                Ok(Message::Cancel(id)) => {
+                    match timers.cancel(&id) {
+                        Some(timer) => println!("Timer {} cancelled", timer.name()),
+                        None => println!("Unknown timer: {}", id)
+                    }
+                },
+                // Start, show or control the pomodoro
+                Ok(Message::Pomodoro(argument)) => {
+                    let now = Local::now();
+                    let done = pomodoro::parse_action(&argument).and_then(|action| match (action, running_pomodoro.as_mut()) {
+                        (Action::Start(None), Some(running)) | (Action::Status, Some(running)) => Ok(format!("Pomodoro: {}", running.status(&now))),
+                        (Action::Start(_), Some(_)) => Err("A pomodoro is running, 'pomodoro stop' ends it".to_string()),
+                        (Action::Start(plan), None) => {
+                            pomodoro::check_sounds(&config_directory)?;
+                            let started = Pomodoro::start(plan.unwrap_or_default(), &now)?;
+                            if ringing.is_none() {
+                                let _send_result = tx_alarm_runner.send(Message::Play(started.sound(&config_directory)));
+                            }
+                            let done = format!("Pomodoro started: {}", started.status(&now));
+                            running_pomodoro = Some(started);
+                            Ok(done)
+                        },
+                        (Action::Pause, Some(running)) => running.pause(&now).map(|_| format!("Pomodoro paused: {}", running.status(&now))),
+                        (Action::Resume, Some(running)) => running.resume(&now).map(|_| format!("Pomodoro resumed: {}", running.status(&now))),
+                        (Action::Skip, Some(running)) => {
+                            running.next(&now);
+                            if ringing.is_none() && !running.is_paused() {
+                                let _send_result = tx_alarm_runner.send(Message::Play(running.sound(&config_directory)));
+                            }
+                            Ok(format!("Pomodoro: {}", running.status(&now)))
+                        },
+                        (Action::Stop, Some(_)) => {
+                            running_pomodoro = None;
+                            Ok("Pomodoro stopped".to_string())
+                        },
+                        (_, None) => Err("No pomodoro running, 'pomodoro' starts one".to_string())
+                    });
+                    match done {
+                        Ok(done) => println!("{}", done),
+                        Err(err) => println!("Pomodoro not changed: {}", err)
+                    }
                 },
                 // Stop the thread
                 Ok(Message::Quit) => {
-                    watcher.unwatch(path2).unwrap();
                     let _send_result = tx_alarm_runner.send(Message::Quit);
                     break;
                 },
@@ -90,36 +399,77 @@ fn main() {
             };
 
             let current_time = chrono::Local::now();
-            println!("{:#02}:{:#02}:{:#02}", current_time.hour(), current_time.minute(), current_time.second());
+            match running_pomodoro {
+                Some(ref running) => println!("{:#02}:{:#02}:{:#02} pomodoro {}", current_time.hour(), current_time.minute(), current_time.second(), running.status(&current_time)),
+                None => println!("{:#02}:{:#02}:{:#02}", current_time.hour(), current_time.minute(), current_time.second())
+            }
 
-            let mut to_remove: Option<usize> = None;
+            if state.expire(&mut scheduler.alarms, &current_time) {
+                if let Err(err) = state.save(&state_file) {
+                    println!("{}", err);
+                }
+            }
 
-            for (index, alarm_config) in alarms.iter().enumerate() {
-                if alarm_config.is_active_day(&current_time) {
-                    if alarm_config.is_expired(&current_time) {
-                        if !alarm_config.already_run_today(&current_time) {
-                            println!("starting alarm {}", current_time);
-                            to_remove = Some(index);
-                            let _send_result = tx_alarm_runner.send(Message::AudioAndVolume(alarm_config.audio_file.clone(), alarm_config.volume));
+            let mut started = scheduler.tick(&current_time);
+            let counted = started.clone();
+            started.extend(snoozed.iter().filter(|&&(until, _)| until <= current_time).map(|&(_, index)| index));
+            snoozed.retain(|&(until, _)| until > current_time);
+
+            for index in started.iter() {
+                let alarm_config = &scheduler.alarms[*index];
+                // reminders play their short sound once, the chime of the configuration directory when they have none,
+                // unless it would cut another sound
+                if alarm_config.is_reminder() {
+                    let text: Vec<&str> = alarm_config.label.iter().chain(alarm_config.message.iter()).map(|s| s.as_str()).collect();
+                    println!("reminder at {}{}", current_time.format("%H:%M:%S"), if text.is_empty() { String::new() } else { format!(": {}", text.join(": ")) });
+                    if ringing.is_none() {
+                        let mut sound = Sound::from(alarm_config);
+                        if sound.audio_file.is_empty() {
+                            sound.audio_file = timer::default_audio(&config_directory);
                         }
+                        let _send_result = tx_alarm_runner.send(Message::Chime(sound));
                     }
+                    continue;
                 }
-            };
+                match alarm_config.label {
+                    Some(ref label) => println!("starting alarm {} {}", label, current_time),
+                    None => println!("starting alarm {}", current_time)
+                }
+                if let Some(banner) = alarm_config.banner() {
+                    println!("{}", banner);
+                }
+                ringing = Some(*index);
+                let _send_result = tx_alarm_runner.send(Message::Play(Sound::from(alarm_config)));
+            }
+            count_runs(&scheduler, &counted, &mut state, &state_file, &tx_main);
 
-            if let Some(i) = to_remove {
-                let cfg = alarms[i].clone();
-                let updated_alarm = AlarmConfig {
-                    last_run: current_time,
-                    ..cfg
-                };
+            // a timer does not cut a ringing alarm, which can still be stopped or snoozed, it waits for it
+            for timer in timers.due(&current_time).into_iter() {
+                match ringing {
+                    Some(index) => {
+                        println!("Timer {} is over, it rings when alarm {} stops", timer.name(), index + 1);
+                        waiting_timers.push(timer);
+                    },
+                    None => {
+                        println!("Timer {} is over", timer.name());
+                        let _send_result = tx_alarm_runner.send(Message::Play(timer.sound()));
+                    }
+                }
+            }
 
-                alarms[i] = updated_alarm;
+            // the sound of a phase does not cut a ringing alarm
+            if let Some(ref mut running) = running_pomodoro {
+                if running.tick(&current_time).is_some() {
+                    println!("Pomodoro: {}", running.status(&current_time));
+                    if ringing.is_none() {
+                        let _send_result = tx_alarm_runner.send(Message::Play(running.sound(&config_directory)));
+                    }
+                }
+            }
 
-            } else if current_time.minute() == 0 && current_time.second() == 0 {
+            if started.is_empty() && current_time.minute() == 0 && current_time.second() == 0 {
                 println!("[INFO]");
-                for alarm in alarms.iter() {
-                    alarm.pretty_print(&current_time)
-                }
+                print_alarms(&scheduler, &current_time);
             }
         }
 
@@ -129,38 +479,64 @@ fn main() {
     // read input
     let input_watcher = input::watch_input(tx_keyboard_input);
 
-    let load_config = |config_file: &str|{
-        let updated_config = config::retrieve_configuration(config_file);
-        let _send_result = tx_alarm_manager.send(Message::Reconfigure(updated_config));
+    let send_config = |alarms: &[AlarmConfig]| {
+        let _send_result = tx_alarm_manager.send(Message::Reconfigure(alarms.to_vec()));
     };
 
-
     // loading the configuration without file system change is required at startup
-    load_config(&config_file);
+    // the configuration sent to the alarm manager is kept to find the alarms to change
+    // and to skip the reloads that do not change it, such as the ones caused by our own changes
+    let mut alarms = config::retrieve_configuration(&config_file);
+    send_config(&alarms);
 
     loop {
 
         match rx_config_update.recv_timeout(channel_wait_timeout) {
-            Ok(_event) => {
-                println!("Reloading configuration");
-                load_config(&config_file);
+            Ok(event) => {
+                if paths::concerns_configuration(&event, &watched_files, &conf_d) {
+                    // the link may now point to another file
+                    let config_files = paths::configuration_files(Path::new(&config_file), &path);
+                    if config_files != watched_files {
+                        watch_link_targets(&mut watcher, &config_files, &path);
+                        watched_files = config_files;
+                    }
+                    let (reloaded, diagnostics) = config::load_configuration(&config_file);
+                    if !config::same_configuration(&alarms, &reloaded) {
+                        println!("Reloading configuration");
+                        for diagnostic in diagnostics.iter() {
+                            println!("{}", diagnostic);
+                        }
+                        alarms = reloaded;
+                        send_config(&alarms);
+                    }
+                }
             },
             Err(mpsc::RecvTimeoutError::Timeout) => {},
             Err(e) => println!("Error while watching configuration file: {:?}", e)
         };
 
         match rx_keyboard_input.recv_timeout(channel_wait_timeout) {
-            Ok(Message::StopAlarm)=> {
-                let _send_result = tx_alarm_manager.send(Message::StopAlarm);
-            },
-            Ok(Message::Show)=> {
-                let _send_result = tx_alarm_manager.send(Message::Show);
-            },
             Ok(Message::Quit) =>     {
+                let _unwatch_result = watcher.unwatch(&path);
                 let _send_result = tx_alarm_manager.send(Message::Quit);
                 break;
             },
-            Err(mpsc::RecvTimeoutError::Timeout) | Ok(_) => {},
+            Ok(message @ Message::Add(_)) | Ok(message @ Message::Edit(_, _)) | Ok(message @ Message::Remove(_))
+            | Ok(message @ Message::Enable(_)) | Ok(message @ Message::Disable(_))
+            | Ok(message @ Message::Pause(_)) | Ok(message @ Message::Resume) | Ok(message @ Message::CountReached(_)) => {
+                match change_configuration(&message, Path::new(&config_file), &alarms) {
+                    Ok((changed, done)) => {
+                        println!("{}", done);
+                        alarms = changed;
+                        send_config(&alarms);
+                    },
+                    Err(err) => println!("Configuration not changed: {}", err)
+                }
+            },
+            Ok(message) => {
+                let _send_result = tx_alarm_manager.send(message);
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => {},
             Err(e) => println!("watch error: {:?}", e)
         };
     }
@@ -0,0 +1,429 @@
+use chrono::prelude::*;
+use chrono::{Duration, NaiveDateTime};
+use chrono_tz::Tz;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use zone::Zone;
+
+/// Start of a calendar event
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventStart {
+    /// Date and time without time zone, read in the time zone of the alarm
+    Floating(NaiveDateTime),
+    /// Instant given in UTC or with a known time zone
+    Fixed(DateTime<Utc>)
+}
+
+impl EventStart {
+
+    /// Date and time in a time zone
+    pub fn in_zone(&self, zone: Zone) -> NaiveDateTime {
+        match *self {
+            EventStart::Floating(naive) => naive,
+            EventStart::Fixed(instant) => zone.naive(&instant)
+        }
+    }
+}
+
+/// Frequency of a recurrence rule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly
+}
+
+/// Recurrence rule of an event, the daily, weekly, monthly and yearly rules of `RRULE`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Number of periods between two occurrences
+    pub interval: u32,
+    /// Number of occurrences, the first one included
+    pub count: Option<u32>,
+    /// Start of the last possible occurrence
+    pub until: Option<EventStart>,
+    /// Days of the week of the occurrences of daily and weekly rules, all days when empty
+    pub by_day: Vec<Weekday>,
+    /// First day of the week, to count the weeks of weekly rules
+    pub week_start: Weekday
+}
+
+impl Recurrence {
+
+    /// Checks if the rule of an event starting on `first` has an occurrence on a day, without count or end
+    fn matches(&self, first: NaiveDate, day: NaiveDate) -> bool {
+        if day == first {
+            return true;
+        }
+        if day < first {
+            return false;
+        }
+        let interval = self.interval as i64;
+        let on_day = |default: Weekday| if self.by_day.is_empty() { day.weekday() == default } else { self.by_day.contains(&day.weekday()) };
+
+        match self.frequency {
+            Frequency::Daily => (day - first).num_days() % interval == 0 && (self.by_day.is_empty() || self.by_day.contains(&day.weekday())),
+            Frequency::Weekly => {
+                let week_of = |date: NaiveDate| date - Duration::days(((date.weekday().num_days_from_monday() + 7 - self.week_start.num_days_from_monday()) % 7) as i64);
+                (week_of(day) - week_of(first)).num_days() / 7 % interval == 0 && on_day(first.weekday())
+            },
+            Frequency::Monthly => {
+                let months = (day.year() - first.year()) as i64 * 12 + day.month() as i64 - first.month() as i64;
+                day.day() == first.day() && months % interval == 0
+            },
+            Frequency::Yearly => day.month() == first.month() && day.day() == first.day() && (day.year() - first.year()) as i64 % interval == 0
+        }
+    }
+}
+
+/// Calendar event read from an ICS file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub start: EventStart,
+    pub summary: String,
+    pub categories: Vec<String>,
+    /// Time zone of the start, in which the occurrences of a recurring event keep their time
+    pub timezone: Option<Tz>,
+    pub recurrence: Option<Recurrence>,
+    /// Starts of the occurrences removed by `EXDATE` or replaced by an event with a `RECURRENCE-ID`
+    pub exceptions: Vec<EventStart>
+}
+
+impl Event {
+
+    /// Start of the event in a time zone
+    pub fn start_in(&self, zone: Zone) -> NaiveDateTime {
+        self.start.in_zone(zone)
+    }
+
+    /// Checks if the event belongs to the category, case insensitive
+    pub fn has_category(&self, category: &str) -> bool {
+        self.categories.iter().any(|c| c.eq_ignore_ascii_case(category))
+    }
+
+    /// Date and time of a start in the time zone of the event, where recurrences are computed
+    fn local(&self, start: &EventStart) -> NaiveDateTime {
+        match (start, self.timezone) {
+            (&EventStart::Floating(naive), _) => naive,
+            (&EventStart::Fixed(instant), Some(tz)) => instant.with_timezone(&tz).naive_local(),
+            (&EventStart::Fixed(instant), None) => instant.naive_utc()
+        }
+    }
+
+    /// Start given by a date and time in the time zone of the event
+    fn to_start(&self, naive: NaiveDateTime) -> Option<EventStart> {
+        match (&self.start, self.timezone) {
+            (&EventStart::Floating(_), _) => Some(EventStart::Floating(naive)),
+            (&EventStart::Fixed(_), Some(tz)) => tz.from_local_datetime(&naive).earliest().map(|dt| EventStart::Fixed(dt.with_timezone(&Utc))),
+            (&EventStart::Fixed(_), None) => Some(EventStart::Fixed(Utc.from_utc_datetime(&naive)))
+        }
+    }
+
+    /// Start of the occurrence of a recurrence on a day of the time zone of the event
+    fn occurrence_on(&self, recurrence: &Recurrence, day: NaiveDate) -> Option<EventStart> {
+        let first = self.local(&self.start);
+        if !recurrence.matches(first.date(), day) {
+            return None;
+        }
+        let naive = day.and_time(first.time());
+        if recurrence.until.as_ref().is_some_and(|until| naive > self.local(until)) {
+            return None;
+        }
+        if let Some(count) = recurrence.count {
+            let rank = first.date().iter_days()
+                .take_while(|date| *date <= day)
+                .filter(|date| recurrence.matches(first.date(), *date))
+                .take(count as usize + 1)
+                .count();
+            if rank > count as usize {
+                return None;
+            }
+        }
+        self.to_start(naive).filter(|start| !self.exceptions.iter().any(|exception| self.local(exception) == self.local(start)))
+    }
+
+    /// Start of the event, or of its first occurrence, on a given day of a time zone
+    pub fn start_on(&self, date: NaiveDate, zone: Zone) -> Option<NaiveDateTime> {
+        match self.recurrence {
+            None => Some(self.start_in(zone)).filter(|start| start.date() == date),
+            // the day in the time zone of the event may be the day before or after
+            Some(ref recurrence) => [date.pred_opt(), Some(date), date.succ_opt()].iter()
+                .flatten()
+                .filter_map(|day| self.occurrence_on(recurrence, *day))
+                .map(|start| start.in_zone(zone))
+                .filter(|start| start.date() == date)
+                .min()
+        }
+    }
+}
+
+/// Unfolds ICS content lines (continuation lines start with a space or a tab),
+/// with the number of the line each one starts on
+fn unfold(content: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = vec![];
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, last)) = lines.last_mut() {
+                last.push_str(&line[1..]);
+            }
+        } else {
+            lines.push((number + 1, line.to_string()));
+        }
+    }
+
+    lines
+}
+
+#[test]
+fn test_unfold() {
+    assert_eq!(unfold("A:b\r\n c\r\nD:e"), vec![(1, "A:bc".to_string()), (3, "D:e".to_string())]);
+    assert_eq!(unfold("A:b\n\tc"), vec![(1, "A:bc".to_string())]);
+}
+
+/// Parses an ICS date-time value with its optional `TZID` parameter
+/// Values ending with `Z` are UTC, values without known time zone are floating
+/// Date only values (all-day events) have no start time and are ignored
+fn parse_date_time(value: &str, tzid: Option<&str>) -> Option<EventStart> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|dt| EventStart::Fixed(Utc.from_utc_datetime(&dt)))
+    } else {
+        let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        match tzid.and_then(|name| name.parse::<Tz>().ok()) {
+            Some(tz) => tz.from_local_datetime(&naive).earliest().map(|dt| EventStart::Fixed(dt.with_timezone(&Utc))),
+            None => Some(EventStart::Floating(naive))
+        }
+    }
+}
+
+#[test]
+fn test_parse_date_time() {
+    let naive = NaiveDate::from_ymd_opt(2026, 10, 20).unwrap().and_hms_opt(7, 30, 0).unwrap();
+    assert_eq!(parse_date_time("20261020T073000", None), Some(EventStart::Floating(naive)));
+    assert_eq!(parse_date_time("20261020T073000", Some("Unknown/Zone")), Some(EventStart::Floating(naive)));
+    assert_eq!(parse_date_time("20261020T073000Z", None), Some(EventStart::Fixed(Utc.from_utc_datetime(&naive))));
+    assert_eq!(parse_date_time("20261020T073000", Some("Europe/Paris")), Some(EventStart::Fixed(Utc.with_ymd_and_hms(2026, 10, 20, 5, 30, 0).unwrap())));
+    assert_eq!(parse_date_time("20261020", None), None);
+    assert_eq!(parse_date_time("garbage", None), None);
+}
+
+/// Weekday written as in `BYDAY` and `WKST`
+fn parse_weekday(s: &str) -> Option<Weekday> {
+    match s {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None
+    }
+}
+
+/// Parses a `RRULE` value, rejecting the parts that are not supported
+/// A date only `UNTIL` includes the whole day
+fn parse_recurrence(value: &str) -> Result<Recurrence, String> {
+    let mut recurrence = Recurrence { frequency: Frequency::Daily, interval: 1, count: None, until: None, by_day: vec![], week_start: Weekday::Mon };
+    let mut frequency: Option<Frequency> = None;
+
+    for part in value.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part.split_once('=').ok_or(format!("Invalid recurrence part: {}", part))?;
+        match key.to_uppercase().as_str() {
+            "FREQ" => frequency = Some(match value.to_uppercase().as_str() {
+                "DAILY" => Frequency::Daily,
+                "WEEKLY" => Frequency::Weekly,
+                "MONTHLY" => Frequency::Monthly,
+                "YEARLY" => Frequency::Yearly,
+                _ => return Err(format!("Unsupported recurrence frequency: {}", value))
+            }),
+            "INTERVAL" => recurrence.interval = value.parse().ok().filter(|interval| *interval > 0).ok_or(format!("Invalid recurrence interval: {}", value))?,
+            "COUNT" => recurrence.count = Some(value.parse().map_err(|_| format!("Invalid recurrence count: {}", value))?),
+            "UNTIL" if value.len() == 8 => recurrence.until = NaiveDate::parse_from_str(value, "%Y%m%d").ok()
+                .and_then(|date| date.and_hms_opt(23, 59, 59))
+                .map(EventStart::Floating)
+                .map(Some)
+                .ok_or(format!("Invalid recurrence end: {}", value))?,
+            "UNTIL" => recurrence.until = Some(parse_date_time(value, None).ok_or(format!("Invalid recurrence end: {}", value))?),
+            "BYDAY" => recurrence.by_day = value.split(',')
+                .map(|day| parse_weekday(&day.to_uppercase()).ok_or(format!("Unsupported recurrence day: {}", day)))
+                .collect::<Result<Vec<Weekday>, String>>()?,
+            "WKST" => recurrence.week_start = parse_weekday(&value.to_uppercase()).ok_or(format!("Invalid week start: {}", value))?,
+            _ => return Err(format!("Unsupported recurrence part: {}", part))
+        }
+    }
+
+    recurrence.frequency = frequency.ok_or("Missing recurrence frequency")?;
+    if !recurrence.by_day.is_empty() && (recurrence.frequency == Frequency::Monthly || recurrence.frequency == Frequency::Yearly) {
+        return Err(format!("Unsupported recurrence days of a monthly or yearly rule: {}", value));
+    }
+    Ok(recurrence)
+}
+
+#[test]
+fn test_parse_recurrence() {
+    let weekly = parse_recurrence("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;UNTIL=20261231T230000Z").unwrap();
+    assert_eq!(weekly.frequency, Frequency::Weekly);
+    assert_eq!(weekly.interval, 2);
+    assert_eq!(weekly.by_day, vec![Weekday::Mon, Weekday::Thu]);
+    assert_eq!(weekly.until, Some(EventStart::Fixed(Utc.with_ymd_and_hms(2026, 12, 31, 23, 0, 0).unwrap())));
+    assert_eq!(parse_recurrence("FREQ=DAILY;COUNT=5;UNTIL=20261231").unwrap().count, Some(5));
+    assert!(parse_recurrence("FREQ=MONTHLY;BYDAY=1MO").is_err());
+    assert!(parse_recurrence("FREQ=WEEKLY;BYDAY=1MO").is_err());
+    assert!(parse_recurrence("FREQ=MONTHLY;BYMONTHDAY=-1").is_err());
+    assert!(parse_recurrence("FREQ=HOURLY").is_err());
+    assert!(parse_recurrence("INTERVAL=2").is_err());
+}
+
+/// Event being read, until its end
+#[derive(Default)]
+struct PendingEvent {
+    line: usize,
+    uid: Option<String>,
+    start: Option<EventStart>,
+    timezone: Option<Tz>,
+    summary: String,
+    categories: Vec<String>,
+    recurrence: Option<String>,
+    recurrence_id: Option<EventStart>,
+    exceptions: Vec<EventStart>,
+    extra_starts: Vec<EventStart>
+}
+
+/// Parses the events of an ICS calendar
+/// Recurring events keep their recurrence, `RDATE` occurrences are read as events of their own,
+/// and the occurrences replaced by an event with a `RECURRENCE-ID` are removed from the recurring event
+/// Events with a recurrence rule other than daily, weekly, monthly and yearly rules are skipped,
+/// with a warning naming them
+pub fn parse_events(content: &str) -> (Vec<Event>, Vec<String>) {
+    let mut events: Vec<Event> = vec![];
+    let mut warnings: Vec<String> = vec![];
+    // UID of the events with a recurrence
+    let mut recurring: Vec<(String, usize)> = vec![];
+    // occurrences replaced by other events, by UID
+    let mut replaced: Vec<(String, EventStart)> = vec![];
+    let mut current: Option<PendingEvent> = None;
+
+    for (number, line) in unfold(content) {
+        let (name_and_params, value) = match line.find(':') {
+            Some(index) => (&line[..index], &line[index + 1..]),
+            None => continue
+        };
+        let mut params = name_and_params.split(';');
+        let name = params.next().unwrap_or("").to_uppercase();
+        let tzid = params.find_map(|param| param.strip_prefix("TZID="));
+
+        if let ("BEGIN", "VEVENT") = (name.as_str(), value) {
+            current = Some(PendingEvent { line: number, ..PendingEvent::default() });
+            continue;
+        }
+        let event = match current {
+            Some(ref mut event) => event,
+            None => continue
+        };
+
+        match name.as_str() {
+            "END" if value == "VEVENT" => {
+                let event = current.take().unwrap_or_default();
+                let start = match event.start.clone() {
+                    Some(start) => start,
+                    None => continue
+                };
+                let recurrence = match event.recurrence.as_deref().map(parse_recurrence) {
+                    Some(Ok(recurrence)) => Some(recurrence),
+                    Some(Err(err)) => {
+                        let name = event.uid.as_deref().unwrap_or(&event.summary);
+                        warnings.push(format!("Skipped event {} at line {}: {}", name, event.line, err));
+                        continue;
+                    },
+                    None => None
+                };
+                if let (Some(uid), Some(recurrence_id)) = (event.uid.clone(), event.recurrence_id) {
+                    replaced.push((uid, recurrence_id));
+                }
+                for extra_start in event.extra_starts.into_iter() {
+                    events.push(Event { start: extra_start, summary: event.summary.clone(), categories: event.categories.clone(), timezone: event.timezone, recurrence: None, exceptions: vec![] });
+                }
+                if let (Some(uid), true) = (event.uid, recurrence.is_some()) {
+                    recurring.push((uid, events.len()));
+                }
+                events.push(Event { start, summary: event.summary, categories: event.categories, timezone: event.timezone, recurrence, exceptions: event.exceptions });
+            },
+            "UID" => event.uid = Some(value.to_string()),
+            "DTSTART" => {
+                event.start = parse_date_time(value, tzid);
+                event.timezone = tzid.and_then(|name| name.parse::<Tz>().ok());
+            },
+            "SUMMARY" => event.summary = value.to_string(),
+            "CATEGORIES" => event.categories.extend(value.split(',').map(|c| c.trim().to_string())),
+            "RRULE" => event.recurrence = Some(value.to_string()),
+            "RECURRENCE-ID" => event.recurrence_id = parse_date_time(value, tzid),
+            "EXDATE" => event.exceptions.extend(value.split(',').filter_map(|value| parse_date_time(value, tzid))),
+            "RDATE" => event.extra_starts.extend(value.split(',').filter_map(|value| parse_date_time(value, tzid))),
+            _ => {}
+        }
+    }
+
+    for (uid, start) in replaced.into_iter() {
+        for &(_, index) in recurring.iter().filter(|(recurring_uid, _)| *recurring_uid == uid) {
+            events[index].exceptions.push(start.clone());
+        }
+    }
+    (events, warnings)
+}
+
+/// Events of the calendar files already read, with the day they were read on and the modification time of the file
+#[allow(clippy::type_complexity)]
+static READ_EVENTS: Mutex<Vec<(String, NaiveDate, Option<SystemTime>, Arc<Vec<Event>>)>> = Mutex::new(Vec::new());
+
+/// Reads the events of an ICS calendar file
+/// A file is read again on the next day or when it changes, its events are kept in between
+/// The events skipped while parsing are reported once per version of the file
+pub fn read_events(path: &str) -> Result<Arc<Vec<Event>>, String> {
+    let today = Local::now().date_naive();
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let mut read = READ_EVENTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((_, _, _, events)) = read.iter().find(|entry| entry.0 == path && entry.1 == today && entry.2 == modified) {
+        return Ok(events.clone());
+    }
+
+    let content = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let (events, warnings) = parse_events(&content);
+    for warning in warnings.iter() {
+        report(path, &format!("Calendar {}: {}", path, warning));
+    }
+    let events = Arc::new(events);
+    read.retain(|entry| entry.0 != path);
+    read.push((path.to_string(), today, modified, events.clone()));
+    Ok(events)
+}
+
+/// Messages already printed about calendar files, with the modification time of the file
+static REPORTED: Mutex<Vec<(String, Option<SystemTime>, String)>> = Mutex::new(Vec::new());
+
+/// Prints a message about a calendar file once until the file changes
+pub fn report(path: &str, message: &str) {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let mut reported = REPORTED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if reported.iter().any(|entry| entry.0 == path && entry.1 == modified && entry.2 == message) {
+        return;
+    }
+    reported.retain(|entry| entry.0 != path || entry.1 == modified);
+    reported.push((path.to_string(), modified, message.to_string()));
+    println!("{}", message);
+}
+
+/// Finds the first event starting on a given day of a time zone, optionally restricted to a category,
+/// with its start on that day in the time zone
+pub fn first_event_on<'a>(events: &'a [Event], date: NaiveDate, category: Option<&str>, zone: Zone) -> Option<(&'a Event, NaiveDateTime)> {
+    events.iter()
+        .filter(|event| category.is_none_or(|c| event.has_category(c)))
+        .filter_map(|event| event.start_on(date, zone).map(|start| (event, start)))
+        .min_by_key(|&(_, start)| start)
+}
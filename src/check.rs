@@ -0,0 +1,120 @@
+use calendar;
+use config::{load_configuration, AlarmConfig, Diagnostic, Schedule, Severity};
+use paths;
+use runner;
+use std::path::Path;
+use timer;
+
+/// Seconds of the day during which an alarm may run,
+/// None when the time is computed each day and for reminders, whose short sound does not get in the way
+fn time_span(alarm: &AlarmConfig) -> Option<(u32, u32)> {
+    let start = alarm.time.to_seconds();
+    match alarm.schedule {
+        Schedule::Fixed => Some((start, start)),
+        Schedule::Random(ref until) => Some((start, until.to_seconds())),
+        Schedule::Calendar(_) | Schedule::Solar(_) | Schedule::Interval(_) => None
+    }
+}
+
+/// Problems found by comparing an alarm to an alarm defined before it
+fn compare(alarm: &AlarmConfig, other: &AlarmConfig) -> Option<String> {
+    let shared_day = alarm.days.iter().zip(other.days.iter()).any(|(day, other_day)| *day && *other_day);
+    if !shared_day || alarm.timezone != other.timezone {
+        return None;
+    }
+
+    match (time_span(alarm), time_span(other)) {
+        (Some((start, end)), Some(other_span)) if start == end && other_span == (start, end) =>
+            Some(format!("Same time {} as the alarm at {}", alarm.time, other.origin)),
+        (Some((start, end)), Some((other_start, other_end))) if start <= other_end && other_start <= end =>
+            Some(format!("Time overlaps the alarm at {}", other.origin)),
+        _ => None
+    }
+}
+
+/// Reads a configuration like `load_configuration`, and also reports:
+/// - audio files of alarms that do not decode and calendars that can not be read, as errors
+/// - audio files of reminders that do not decode, the chime of the configuration directory for those without one, as warnings
+/// - alarms without any active day, as warnings
+/// - alarms running at the same time or in overlapping windows on a same day, as warnings
+pub fn check(path: &str) -> (Vec<AlarmConfig>, Vec<Diagnostic>) {
+    let (alarms, mut diagnostics) = load_configuration(path);
+
+    let chime = timer::default_audio(&paths::directory_of(Path::new(path)));
+
+    for (index, alarm) in alarms.iter().enumerate() {
+        // a reminder whose short sound does not play still shows its message
+        if alarm.is_reminder() {
+            let audio_file = if alarm.audio_file.is_empty() { &chime } else { &alarm.audio_file };
+            if let Err(err) = runner::check_audio(audio_file) {
+                diagnostics.push(Diagnostic::warning(alarm.origin.clone(), format!("The reminder only shows its message: {}", err)));
+            }
+        } else if let Err(err) = runner::check_audio(&alarm.audio_file) {
+            diagnostics.push(Diagnostic::error(alarm.origin.clone(), err));
+        }
+        if let Schedule::Calendar(ref rule) = alarm.schedule {
+            if let Err(err) = calendar::read_events(&rule.path) {
+                diagnostics.push(Diagnostic::error(alarm.origin.clone(), format!("Unable to read calendar {}: {}", rule.path, err)));
+            }
+        }
+        if !alarm.days.contains(&true) {
+            diagnostics.push(Diagnostic::warning(alarm.origin.clone(), "Alarm never runs, it has no active day".to_string()));
+        }
+        for other in alarms[..index].iter() {
+            if let Some(message) = compare(alarm, other) {
+                diagnostics.push(Diagnostic::warning(alarm.origin.clone(), message));
+            }
+        }
+    }
+
+    // in the order of the files
+    diagnostics.sort_by(|a, b| (&a.origin.file, a.origin.line).cmp(&(&b.origin.file, b.origin.line)));
+    (alarms, diagnostics)
+}
+
+/// Checks if some problems are errors
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+#[test]
+fn test_check() {
+    let directory = ::tests::TempDir::new("check");
+    directory.write("chime.wav", ::init::chime_wav());
+    directory.write("noise.wav", "not audio");
+    directory.write("config", "audio=chime.wav
+Mon-Fri;06:30;;
+weekdays;6:30am;;
+MTWTF__;06:30:00;;;timezone=Europe/Paris
+weekends;06:30;;
+Mon;06:20..06:40;;
+_______;07:00;;
+daily;08:00;missing.ogg;
+daily;09:00;noise.wav;
+daily;10:00;;;calendar=missing.ics
+daily;noon;;
+daily;13:00..14:00;noise.wav;;every=30m
+");
+
+    let (alarms, diagnostics) = check(directory.join("config").to_str().unwrap());
+    assert_eq!(alarms.len(), 10);
+
+    // paths relative to the directory of the configuration
+    let messages: Vec<String> = diagnostics.iter()
+        .map(|diagnostic| diagnostic.to_string().replace(&format!("{}/", directory.path.canonicalize().unwrap().display()), "")
+            .replace(&format!("{}/", directory.path.display()), ""))
+        .collect();
+    assert_eq!(messages, vec![
+        "config:3: warning: Same time 06:30:00 as the alarm at config:2",
+        "config:6: warning: Time overlaps the alarm at config:2",
+        "config:6: warning: Time overlaps the alarm at config:3",
+        "config:7: warning: Alarm never runs, it has no active day",
+        "config:8: error: Unable to open missing.ogg: No such file or directory (os error 2)",
+        "config:9: error: Unable to decode noise.wav: Unrecognized format",
+        "config:10: error: Unable to read calendar missing.ics: No such file or directory (os error 2)",
+        "config:11: error: Invalid configuration: Invalid time: noon",
+        "config:12: warning: The reminder only shows its message: Unable to decode noise.wav: Unrecognized format"
+    ]);
+    assert!(has_errors(&diagnostics));
+    assert!(!has_errors(&diagnostics[..4]));
+}
@@ -0,0 +1,549 @@
+use chrono::prelude::*;
+use check;
+use config;
+use config::{load_configuration, parse_date, AlarmConfig, Diagnostic, Exception, Schedule, Severity};
+use config::edit;
+use init;
+use paths;
+use scheduler::Scheduler;
+use serde_json;
+use simulate;
+use state::State;
+use zone::Zone;
+use serde_json::Value;
+use std::path::Path;
+
+/// Command line usage
+pub const USAGE: &str = "Usage: rustine-bin [--config <file>] [--json] [run | init | check [file] | list | next \
+    | agenda [days] [--from <date>] [--to <date>] | add <line> | remove <alarm> | enable <alarm> | disable <alarm> \
+    | pause <date> | resume | simulate [days] [--from <date>] [--to <date>]]";
+
+/// Period of the `agenda` and `simulate` commands, dates are read in the system time zone
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Period {
+    /// First day, from now when not given
+    pub from: Option<NaiveDate>,
+    /// Last day, included
+    pub to: Option<NaiveDate>,
+    /// Number of days, 7 when neither it nor the last day are given
+    pub days: Option<u32>
+}
+
+impl Period {
+
+    /// Start and end instants of the period
+    pub fn bounds(&self, now: &DateTime<Local>) -> Result<(DateTime<Local>, DateTime<Local>), String> {
+        let midnight = |date: NaiveDate| Zone::Local.resolve(&date.and_time(NaiveTime::MIN)).ok_or(format!("Invalid date: {}", date));
+
+        let from = match self.from {
+            Some(date) => midnight(date)?,
+            None => *now
+        };
+        let to = match (self.to, self.days) {
+            (Some(_), Some(_)) => return Err("Give either a number of days or --to".to_string()),
+            (Some(date), None) => midnight(date.succ_opt().ok_or(format!("Invalid date: {}", date))?)?,
+            (None, days) => from + chrono::Duration::days(days.unwrap_or(7) as i64)
+        };
+        if to <= from {
+            return Err(format!("The period ends before it starts, on {}", to.format("%Y-%m-%d")));
+        }
+        Ok((from, to))
+    }
+}
+
+#[test]
+fn test_period() {
+    let now = Local.with_ymd_and_hms(2026, 10, 18, 15, 0, 0).unwrap();
+    let date = |day: u32| NaiveDate::from_ymd_opt(2026, 10, day);
+    let midnight = |day: u32| Local.with_ymd_and_hms(2026, 10, day, 0, 0, 0).unwrap();
+
+    assert_eq!(Period::default().bounds(&now), Ok((now, now + chrono::Duration::days(7))));
+    assert_eq!(Period { from: date(1), to: date(3), days: None }.bounds(&now), Ok((midnight(1), midnight(4))));
+    assert_eq!(Period { from: date(1), to: None, days: Some(2) }.bounds(&now), Ok((midnight(1), midnight(3))));
+    assert!(Period { from: None, to: date(31), days: Some(2) }.bounds(&now).is_err());
+    assert!(Period { from: date(20), to: date(19), days: None }.bounds(&now).is_err());
+}
+
+/// Command given on the command line
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Runs the alarms, the default command
+    Run,
+    /// Creates the configuration
+    Init,
+    /// Checks a configuration file, the configuration file when not given
+    Check(Option<String>),
+    /// Lists the alarms with their position
+    List,
+    /// Shows the next alarm to run
+    Next,
+    /// Lists the runs of the alarms in the given period
+    Agenda(Period),
+    /// Adds an alarm written as a line of the CSV-like format
+    Add(String),
+    /// Removes an alarm given by label or position
+    Remove(String),
+    /// Enables an alarm given by label or position
+    Enable(String),
+    /// Disables an alarm given by label or position
+    Disable(String),
+    /// Pauses all the alarms until a day written `YYYY-MM-DD`
+    Pause(String),
+    /// Removes the pause of the alarms
+    Resume,
+    /// Runs the alarms of the given period on a virtual clock, without playing them
+    Simulate(Period)
+}
+
+/// Arguments of the command line
+#[derive(Debug, PartialEq)]
+pub struct Arguments {
+    /// File given with `--config <file>`
+    pub config: Option<String>,
+    /// Output written as JSON, with `--json`
+    pub json: bool,
+    pub command: Command
+}
+
+/// Reads a number of days, at least 1
+fn parse_days(days: Option<&String>) -> Result<Option<u32>, String> {
+    match days {
+        None => Ok(None),
+        Some(days) => days.parse().ok().filter(|days| *days >= 1).map(Some).ok_or(format!("Invalid number of days: {}", days))
+    }
+}
+
+/// Reads the `[--config <file>] [--json] [--from <date>] [--to <date>] [command [argument]]` command line
+/// Options taking a value are written `--option value` or `--option=value`
+pub fn parse_arguments(args: &[String]) -> Result<Arguments, String> {
+    let mut arguments = Arguments { config: None, json: false, command: Command::Run };
+    let mut period = Period::default();
+    let mut positional: Vec<&String> = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (option, inline) = match arg.split_once('=') {
+            Some((option, value)) if arg.starts_with("--") => (option, Some(value.to_string())),
+            _ => (arg.as_str(), None)
+        };
+        let mut value = || inline.clone().or_else(|| args.next().cloned()).ok_or(format!("Missing value after {}", option));
+        match option {
+            "--config" => arguments.config = Some(value()?),
+            "--from" => period.from = Some(parse_date(&value()?)?),
+            "--to" => period.to = Some(parse_date(&value()?)?),
+            "--json" if inline.is_none() => arguments.json = true,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+            _ => positional.push(arg)
+        }
+    }
+
+    let (command, argument) = match positional.as_slice() {
+        [] => ("run", None),
+        [command] => (command.as_str(), None),
+        [command, argument] => (command.as_str(), Some(*argument)),
+        _ => return Err(format!("Too many arguments: {}", positional.iter().map(|arg| arg.as_str()).collect::<Vec<_>>().join(" ")))
+    };
+    let required = |name: &str| argument.cloned().ok_or(format!("Missing {} after {}", name, command));
+
+    arguments.command = match (command, argument) {
+        ("run", None) => Command::Run,
+        ("init", None) => Command::Init,
+        ("check", file) => Command::Check(file.cloned()),
+        ("list", None) => Command::List,
+        ("next", None) => Command::Next,
+        ("agenda", days) => Command::Agenda(Period { days: parse_days(days)?, ..period }),
+        ("add", _) => Command::Add(required("alarm line")?),
+        ("remove", _) => Command::Remove(required("alarm")?),
+        ("enable", _) => Command::Enable(required("alarm")?),
+        ("disable", _) => Command::Disable(required("alarm")?),
+        ("pause", _) => Command::Pause(required("date")?),
+        ("resume", None) => Command::Resume,
+        ("simulate", days) => Command::Simulate(Period { days: parse_days(days)?, ..period }),
+        (_, None) => return Err(format!("Unknown command: {}", command)),
+        (_, Some(argument)) => return Err(format!("Unknown command: {} {}", command, argument))
+    };
+    let dated = matches!(arguments.command, Command::Agenda(_) | Command::Simulate(_));
+    if !dated && (period.from.is_some() || period.to.is_some()) {
+        return Err("Options --from and --to apply to agenda and simulate".to_string());
+    }
+    if arguments.json && arguments.command == Command::Run {
+        return Err("Option --json does not apply to run".to_string());
+    }
+    Ok(arguments)
+}
+
+#[test]
+fn test_parse_arguments() {
+    let parse = |line: &str| parse_arguments(&line.split_whitespace().map(|arg| arg.to_string()).collect::<Vec<_>>());
+
+    assert_eq!(parse(""), Ok(Arguments { config: None, json: false, command: Command::Run }));
+    assert_eq!(parse("--config /tmp/alarms --json list"), Ok(Arguments { config: Some("/tmp/alarms".to_string()), json: true, command: Command::List }));
+    assert_eq!(parse("check --config=/tmp/alarms").map(|arguments| arguments.config), Ok(Some("/tmp/alarms".to_string())));
+    assert_eq!(parse("check alarms.toml").map(|arguments| arguments.command), Ok(Command::Check(Some("alarms.toml".to_string()))));
+    assert_eq!(parse("agenda").map(|arguments| arguments.command), Ok(Command::Agenda(Period::default())));
+    assert_eq!(parse("simulate 30").map(|arguments| arguments.command), Ok(Command::Simulate(Period { days: Some(30), ..Period::default() })));
+    assert_eq!(parse("simulate --from 2026-10-01 --to=2026-12-31").map(|arguments| arguments.command), Ok(Command::Simulate(Period {
+        from: NaiveDate::from_ymd_opt(2026, 10, 1),
+        to: NaiveDate::from_ymd_opt(2026, 12, 31),
+        days: None
+    })));
+    assert!(parse("simulate --from 01/10/2026").is_err());
+    assert!(parse("list --from 2026-10-01").is_err());
+    assert!(parse("--json=yes list").is_err());
+    assert_eq!(parse("disable Gym").map(|arguments| arguments.command), Ok(Command::Disable("Gym".to_string())));
+    assert_eq!(parse("pause 2026-11-02").map(|arguments| arguments.command), Ok(Command::Pause("2026-11-02".to_string())));
+    assert!(parse("pause").is_err());
+    assert!(parse("agenda 0").is_err());
+    assert!(parse("remove").is_err());
+    assert!(parse("list 2").is_err());
+    assert!(parse("--json").is_err());
+    assert!(parse("--verbose list").is_err());
+    assert!(parse("--config").is_err());
+    assert!(parse("snooze").is_err());
+    assert!(parse("add a b").is_err());
+}
+
+/// Alarm written as JSON
+fn alarm_json(index: usize, alarm: &AlarmConfig, now: &DateTime<Local>) -> Value {
+    json!({
+        "position": index + 1,
+        "label": alarm.label,
+        "days": alarm.days_mask(),
+        "time": alarm.time_on(alarm.local_time(now).date()).to_string(),
+        "timezone": alarm.timezone.to_string(),
+        "audio": alarm.audio_file,
+        "volume": alarm.volume,
+        "snooze": alarm.snooze,
+        "fade": alarm.fade,
+        "device": alarm.device,
+        "message": alarm.message,
+        "enabled": !alarm.disabled,
+        "from": alarm.first_day.map(|day| day.to_string()),
+        "to": alarm.last_day.map(|day| day.to_string()),
+        "count": alarm.count,
+        "every": match alarm.schedule {
+            Schedule::Interval(ref rule) => Some(rule.every),
+            _ => None
+        },
+        "runs": alarm.count.map(|_| alarm.runs),
+        "paused_until": alarm.paused_until.filter(|until| *until > now.date_naive()).map(|until| until.to_string()),
+        "next": alarm.next_run(now).map(|run| run.to_rfc3339()),
+        "exception": alarm.exception.as_ref().map(|exception| json!({
+            "run": exception.run.to_rfc3339(),
+            "moved_to": exception.moved_to.map(|moved_to| moved_to.to_rfc3339())
+        })),
+        "origin": alarm.origin.to_string()
+    })
+}
+
+/// Problem of the configuration written as JSON
+fn diagnostic_json(diagnostic: &Diagnostic) -> Value {
+    json!({
+        "file": diagnostic.origin.file.to_string_lossy(),
+        "line": diagnostic.origin.line,
+        "severity": match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        },
+        "message": diagnostic.message
+    })
+}
+
+/// Runs of the alarms written one per line, or as JSON
+/// Runs skipped or moved by the exception of their alarm are marked
+fn print_runs(scheduler: &Scheduler, runs: &[(DateTime<Local>, usize)], json: bool) {
+    let skipped = |run: &DateTime<Local>, index: usize| matches!(scheduler.alarms[index].exception,
+        Some(Exception { run: ref planned, moved_to: None }) if planned == run);
+    let moved_from = |run: &DateTime<Local>, index: usize| match scheduler.alarms[index].exception {
+        Some(Exception { run: planned, moved_to: Some(ref moved_to) }) if moved_to == run => Some(planned),
+        _ => None
+    };
+
+    if json {
+        let runs: Vec<Value> = runs.iter().map(|&(run, index)| json!({
+            "time": run.to_rfc3339(),
+            "position": index + 1,
+            "label": scheduler.alarms[index].label,
+            "skipped": skipped(&run, index),
+            "moved_from": moved_from(&run, index).map(|planned| planned.to_rfc3339())
+        })).collect();
+        print_json(&Value::Array(runs));
+        return;
+    }
+
+    for &(run, index) in runs.iter() {
+        let alarm = &scheduler.alarms[index];
+        let note = match moved_from(&run, index) {
+            Some(planned) => format!(" (moved from {})", planned.format("%H:%M:%S")),
+            None if skipped(&run, index) => " (skipped)".to_string(),
+            None => String::new()
+        };
+        println!("{}  {}. {}{}", run.format("%a %Y-%m-%d %H:%M:%S"), index + 1, alarm.label.as_ref().unwrap_or(&alarm.audio_file), note);
+    }
+}
+
+fn print_json(value: &Value) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+}
+
+/// Prints an error, as JSON on the output or as text on the error output, returns the exit code of a failed command
+fn fail(message: &str, json: bool) -> i32 {
+    if json {
+        print_json(&json!({ "error": message }));
+    } else {
+        eprintln!("{}", message);
+    }
+    1
+}
+
+/// Reads the alarms of the configuration file, with the skipped and moved runs of the state file
+/// Problems are written to the error output, to keep the output readable by scripts
+fn load(config_file: &Path) -> Scheduler {
+    let (mut alarms, diagnostics) = load_configuration(&config_file.to_string_lossy());
+    for diagnostic in diagnostics.iter() {
+        eprintln!("{}", diagnostic);
+    }
+    State::load(&paths::state_file(config_file)).apply(&mut alarms);
+    Scheduler::new(alarms)
+}
+
+/// Creates the configuration and prints a readiness report
+fn run_init(config_file: &Path, json: bool) -> i32 {
+    let checks = init::init(config_file);
+    let ready = checks.iter().all(|check| check.result.is_ok());
+
+    if json {
+        let checks: Vec<Value> = checks.iter().map(|check| json!({
+            "name": check.name,
+            "ok": check.result.is_ok(),
+            "message": match check.result { Ok(ref message) | Err(ref message) => message }
+        })).collect();
+        print_json(&json!({ "checks": checks, "ready": ready }));
+    } else {
+        for check in checks.iter() {
+            match check.result {
+                Ok(ref message) => println!("[ok]   {}: {}", check.name, message),
+                Err(ref message) => println!("[FAIL] {}: {}", check.name, message)
+            }
+        }
+        if ready {
+            println!("Ready, edit {} and run rustine-bin to start the alarms", config_file.display());
+        } else {
+            println!("Not ready, fix the failed checks above");
+        }
+    }
+
+    if ready { 0 } else { 1 }
+}
+
+/// Checks a configuration file and prints the problems found
+fn run_check(config_file: &Path, json: bool) -> i32 {
+    let (alarms, diagnostics) = check::check(&config_file.to_string_lossy());
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).count();
+
+    if json {
+        print_json(&json!({
+            "file": config_file.to_string_lossy(),
+            "alarms": alarms.len(),
+            "errors": errors,
+            "warnings": diagnostics.len() - errors,
+            "diagnostics": diagnostics.iter().map(diagnostic_json).collect::<Vec<_>>()
+        }));
+    } else {
+        for diagnostic in diagnostics.iter() {
+            println!("{}", diagnostic);
+        }
+        println!("{}: {} alarms, {} errors, {} warnings", config_file.display(), alarms.len(), errors, diagnostics.len() - errors);
+    }
+
+    if check::has_errors(&diagnostics) { 1 } else { 0 }
+}
+
+/// Adds an alarm to the configuration file and prints it
+fn run_add(config_file: &Path, line: &str, json: bool) -> i32 {
+    let (alarms, index) = match edit::add_alarm(config_file, line) {
+        Ok(added) => added,
+        Err(err) => return fail(&format!("Unable to add the alarm: {}", err), json)
+    };
+
+    let now = Local::now();
+    let added = &alarms[index];
+    if json {
+        print_json(&alarm_json(index, added, &now));
+    } else {
+        print!("Added alarm {} at {}: ", index + 1, added.origin);
+        added.pretty_print(&now);
+    }
+    0
+}
+
+/// Removes, enables or disables an alarm of the configuration file
+fn run_change(config_file: &Path, command: &Command, json: bool) -> i32 {
+    let scheduler = load(config_file);
+    let (id, action) = match *command {
+        Command::Remove(ref id) => (id, "removed"),
+        Command::Enable(ref id) => (id, "enabled"),
+        Command::Disable(ref id) => (id, "disabled"),
+        _ => return fail("Not a change of the configuration", json)
+    };
+    let index = match scheduler.find(id) {
+        Some(index) => index,
+        None => return fail(&format!("Unknown alarm: {}", id), json)
+    };
+
+    let alarm = &scheduler.alarms[index];
+    let changed = match *command {
+        Command::Remove(_) => edit::remove_alarm(config_file, alarm),
+        _ => edit::set_enabled(config_file, alarm, action == "enabled")
+    };
+    let alarms = match changed {
+        Ok(alarms) => alarms,
+        Err(err) => return fail(&format!("Unable to change alarm {}: {}", id, err), json)
+    };
+    // the removed alarm is shown as it was
+    let alarm = alarms.get(index).filter(|_| action != "removed").unwrap_or(alarm);
+
+    if json {
+        print_json(&json!({ "action": action, "alarm": alarm_json(index, alarm, &Local::now()) }));
+    } else {
+        println!("Alarm {} {} in {}", index + 1, action, alarm.origin);
+    }
+    0
+}
+
+/// Pauses the alarms until a day written `YYYY-MM-DD`, after today, or resumes them when there is no day
+/// Returns the alarms read again with a description of the change
+pub fn change_pause(config_file: &Path, until: Option<&str>) -> Result<(Vec<AlarmConfig>, String), String> {
+    let today = Local::now().date_naive();
+    let until = match until {
+        Some(until) => match parse_date(until)? {
+            until if until <= today => return Err(format!("{} is not after today", until)),
+            until => Some(until)
+        },
+        None => None
+    };
+
+    let alarms = edit::set_pause(config_file, until)?;
+    let done = match (until, config::paused_until(&alarms, today)) {
+        (Some(until), _) => format!("Alarms paused, they run again on {}", until),
+        (None, Some(still)) => format!("Pause removed from {}, another file pauses the alarms until {}", config_file.display(), still),
+        (None, None) => "Alarms resumed".to_string()
+    };
+    Ok((alarms, done))
+}
+
+/// Line shown before the alarms while they are paused
+pub fn pause_banner(alarms: &[AlarmConfig], now: &DateTime<Local>) -> Option<String> {
+    config::paused_until(alarms, now.date_naive())
+        .map(|until| format!("*** PAUSED: alarms run again on {}, 'resume' to run them now ***", until))
+}
+
+/// Runs the alarms of a period on a virtual clock and prints their runs, explained when they are not at the configured time
+fn run_simulate(config_file: &Path, period: &Period, json: bool) -> i32 {
+    let (from, to) = match period.bounds(&Local::now()) {
+        Ok(bounds) => bounds,
+        Err(err) => return fail(&err, json)
+    };
+    let alarms = load(config_file).alarms;
+    let (scheduler, events) = simulate::simulate(alarms, &from, &to);
+
+    if json {
+        print_json(&json!({
+            "from": from.to_rfc3339(),
+            "to": to.to_rfc3339(),
+            "events": events.iter().map(|event| json!({
+                "time": event.time.to_rfc3339(),
+                "position": event.alarm + 1,
+                "label": scheduler.alarms[event.alarm].label,
+                "notes": event.notes
+            })).collect::<Vec<_>>()
+        }));
+        return 0;
+    }
+
+    println!("Simulation from {} to {}: {} runs", from.format("%Y-%m-%d %H:%M"), to.format("%Y-%m-%d %H:%M"), events.len());
+    for event in events.iter() {
+        let alarm = &scheduler.alarms[event.alarm];
+        println!("{}  {}. {}", event.time.format("%a %Y-%m-%d %H:%M:%S %:z"), event.alarm + 1, alarm.label.as_ref().unwrap_or(&alarm.audio_file));
+        for note in event.notes.iter() {
+            println!("    {}", note);
+        }
+    }
+    0
+}
+
+/// Runs a command other than `run`, printing its result as text or JSON
+/// Returns the exit code of the application
+pub fn execute(command: &Command, config_file: &Path, json: bool) -> i32 {
+    let now = Local::now();
+
+    match *command {
+        Command::Run => fail("The run command is not executed here", json),
+        Command::Init => run_init(config_file, json),
+        Command::Check(ref file) => run_check(file.as_ref().map_or(config_file, Path::new), json),
+        Command::List => {
+            let scheduler = load(config_file);
+            if json {
+                print_json(&Value::Array(scheduler.alarms.iter().enumerate().map(|(index, alarm)| alarm_json(index, alarm, &now)).collect()));
+            } else {
+                if let Some(banner) = pause_banner(&scheduler.alarms, &now) {
+                    println!("{}", banner);
+                }
+                for (index, alarm) in scheduler.alarms.iter().enumerate() {
+                    print!("{}. ", index + 1);
+                    alarm.pretty_print(&now);
+                }
+            }
+            0
+        },
+        Command::Next => {
+            let scheduler = load(config_file);
+            match scheduler.next(&now) {
+                Some((run, index)) if json => print_json(&json!({ "time": run.to_rfc3339(), "alarm": alarm_json(index, &scheduler.alarms[index], &now) })),
+                Some((run, index)) => {
+                    println!("Next alarm: {}", run.to_rfc3339());
+                    print!("{}. ", index + 1);
+                    scheduler.alarms[index].pretty_print(&now)
+                },
+                None if json => print_json(&Value::Null),
+                None => println!("No alarm in the coming week")
+            }
+            0
+        },
+        Command::Agenda(period) => {
+            let (from, to) = match period.bounds(&now) {
+                Ok(bounds) => bounds,
+                Err(err) => return fail(&err, json)
+            };
+            let scheduler = load(config_file);
+            let mut runs = scheduler.agenda(&from, &to);
+            // skipped runs are listed too, to show that they will not ring
+            runs.extend(scheduler.alarms.iter()
+                .enumerate()
+                .filter(|&(_, alarm)| !alarm.disabled)
+                .filter_map(|(index, alarm)| match alarm.exception {
+                    Some(Exception { run, moved_to: None }) if run > from && run <= to => Some((run, index)),
+                    _ => None
+                }));
+            runs.sort();
+            print_runs(&scheduler, &runs, json);
+            0
+        },
+        Command::Add(ref line) => run_add(config_file, line, json),
+        Command::Remove(_) | Command::Enable(_) | Command::Disable(_) => run_change(config_file, command, json),
+        Command::Pause(_) | Command::Resume => {
+            let until = match *command {
+                Command::Pause(ref until) => Some(until.as_str()),
+                _ => None
+            };
+            match change_pause(config_file, until) {
+                Ok((alarms, _)) if json => print_json(&json!({
+                    "paused_until": config::paused_until(&alarms, now.date_naive()).map(|until| until.to_string())
+                })),
+                Ok((_, done)) => println!("{}", done),
+                Err(err) => return fail(&format!("Unable to change the pause: {}", err), json)
+            }
+            0
+        },
+        Command::Simulate(period) => run_simulate(config_file, &period, json)
+    }
+}
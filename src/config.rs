@@ -1,52 +1,594 @@
+use calendar;
 use chrono;
 use chrono::DateTime;
 use chrono::prelude::*;
-use std::fs::OpenOptions;
+use paths;
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use solar;
+use solar::SolarEvent;
+use std::cmp;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use timer;
+use zone::Zone;
+
+pub mod edit;
+mod toml_file;
 
 /// Alarm time
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default, Hash)]
 pub struct Time {
     pub hours: u32,
     pub minutes: u32,
     pub seconds: u32
 }
 
-/// Alarm configuration
+impl Time {
+
+    /// Number of seconds since midnight
+    pub fn to_seconds(&self) -> u32 {
+        self.hours * 3600 + self.minutes * 60 + self.seconds
+    }
+
+    /// Time from a number of seconds since midnight, capped to 23:59:59
+    pub fn from_seconds(s: u32) -> Time {
+        let s = cmp::min(s, 86399);
+        Time {
+            hours: s / 3600,
+            minutes: (s % 3600) / 60,
+            seconds: s % 60
+        }
+    }
+
+    /// Date and time of this time on a given day
+    pub fn on(&self, date: NaiveDate) -> NaiveDateTime {
+        date.and_hms_opt(self.hours, self.minutes, self.seconds).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hours, self.minutes, self.seconds)
+    }
+}
+
+/// Alarm set relative to the first event of the day found in an ICS calendar file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarRule {
+    /// Path of the ICS file
+    pub path: String,
+    /// Only events of this category are considered when set
+    pub category: Option<String>,
+    /// Number of seconds between the alarm and the event
+    pub before: u32
+}
+
+/// Alarm set relative to sunrise or sunset at some coordinates
 #[derive(Debug, Clone, PartialEq)]
+pub struct SolarRule {
+    pub event: SolarEvent,
+    /// Number of seconds between the sun event and the alarm, negative when the alarm runs before
+    pub offset: i32,
+    pub latitude: f64,
+    pub longitude: f64
+}
+
+impl SolarRule {
+
+    /// Time of the alarm on a given day of a time zone, None when the sun does not rise or set that day
+    pub fn time_on(&self, date: NaiveDate, zone: Zone) -> Option<Time> {
+        // the sun event of a local day may happen on the previous or next UTC day
+        [date.pred_opt(), Some(date), date.succ_opt()].iter()
+            .flatten()
+            .filter_map(|day| solar::event_time(*day, self.latitude, self.longitude, self.event))
+            .map(|utc| zone.naive(&utc))
+            .find(|local| local.date() == date)
+            .map(|local| Time::from_seconds(cmp::max(local.num_seconds_from_midnight() as i32 + self.offset, 0) as u32))
+    }
+}
+
+#[test]
+fn test_solar_time_on() {
+    let rule = SolarRule { event: SolarEvent::Sunrise, offset: 0, latitude: 69.65, longitude: 18.96 };
+    let oslo = Zone::Named(chrono_tz::Europe::Oslo);
+    // polar night in Tromsø
+    assert_eq!(rule.time_on(NaiveDate::from_ymd_opt(2026, 12, 20).unwrap(), oslo), None);
+    assert!(rule.time_on(NaiveDate::from_ymd_opt(2026, 3, 20).unwrap(), oslo).is_some());
+}
+
+/// Reminder running at a regular interval from the configured time
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalRule {
+    /// Number of seconds between two runs
+    pub every: u32,
+    /// Time after which the reminder does not run that day
+    pub until: Time
+}
+
+/// How the alarm time is computed for a given day
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Schedule {
+    /// Runs at the configured time
+    #[default]
+    Fixed,
+    /// Runs before the first event of the day, at the configured time when there is no event
+    Calendar(CalendarRule),
+    /// Runs relative to sunrise or sunset, at the configured time when the sun does not rise or set
+    Solar(SolarRule),
+    /// Runs at a time drawn each day between the configured time and the given time
+    Random(Time),
+    /// Runs several times a day, a short sound that does not need to be stopped
+    Interval(IntervalRule)
+}
+
+/// Change of a single run of an alarm, kept until that run has passed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exception {
+    /// Instant at which the run was planned
+    pub run: DateTime<Local>,
+    /// Instant the run is moved to, the run is skipped when not set
+    pub moved_to: Option<DateTime<Local>>
+}
+
+/// Settings shared by all the alarms of a configuration file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Settings {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Time zone of the alarms without their own time zone
+    pub timezone: Zone,
+    /// Fields used by the alarms that do not set them
+    pub defaults: AlarmFields,
+    /// Named sets of fields that alarms inherit from with the `template` option
+    pub templates: HashMap<String, AlarmFields>,
+    /// Day on which the alarms run again, set with `pause=YYYY-MM-DD`
+    pub paused_until: Option<NaiveDate>
+}
+
+/// Snooze duration in minutes of the alarms that do not set it
+const DEFAULT_SNOOZE: u32 = 9;
+
+/// Volume of the reminders that do not set it
+const REMINDER_VOLUME: &str = "50";
+
+/// Alarm fields as written in a configuration file, before templates and defaults are applied
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AlarmFields {
+    pub days: Option<String>,
+    pub time: Option<String>,
+    pub audio: Option<String>,
+    pub volume: Option<String>,
+    /// Optional `key=value` fields
+    pub options: Vec<String>
+}
+
+/// Key of a `key=value` option
+fn option_key(option: &str) -> &str {
+    option.split('=').next().unwrap_or(option)
+}
+
+impl AlarmFields {
+
+    /// Value of an option
+    fn option(&self, key: &str) -> Option<&str> {
+        self.options.iter()
+            .filter_map(|option| option.split_once('='))
+            .find(|&(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Fields completed with the fields and options of another set that are missing here
+    pub fn inherit(&self, other: &AlarmFields) -> AlarmFields {
+        let mut options = self.options.clone();
+        options.extend(other.options.iter()
+            .filter(|option| !self.options.iter().any(|own| option_key(own) == option_key(option)))
+            .cloned());
+
+        AlarmFields {
+            days: self.days.clone().or_else(|| other.days.clone()),
+            time: self.time.clone().or_else(|| other.time.clone()),
+            audio: self.audio.clone().or_else(|| other.audio.clone()),
+            volume: self.volume.clone().or_else(|| other.volume.clone()),
+            options
+        }
+    }
+
+    /// Fields with their relative audio and calendar paths resolved against the directory of the file setting them,
+    /// so that the alarms of other files inheriting them find the same files
+    fn anchor(&self, directory: &Path) -> Result<AlarmFields, String> {
+        let mut options: Vec<String> = vec![];
+        for option in self.options.iter() {
+            options.push(match option.split_once('=') {
+                Some(("calendar", path)) => format!("calendar={}", paths::expand(path, directory)?),
+                _ => option.clone()
+            });
+        }
+        Ok(AlarmFields {
+            audio: self.audio.as_ref().map(|audio| paths::expand(audio, directory)).transpose()?,
+            options,
+            ..self.clone()
+        })
+    }
+}
+
+#[test]
+fn test_anchor() {
+    let fields = AlarmFields {
+        audio: Some("sounds/chime.wav".to_string()),
+        options: vec!["calendar=/home/me/work.ics".to_string(), "fade=30".to_string()],
+        ..AlarmFields::default()
+    };
+    assert_eq!(fields.anchor(Path::new("/etc/rustine")), Ok(AlarmFields {
+        audio: Some("/etc/rustine/sounds/chime.wav".to_string()),
+        ..fields.clone()
+    }));
+    assert_eq!(AlarmFields::default().anchor(Path::new("/etc/rustine")), Ok(AlarmFields::default()));
+}
+
+#[test]
+fn test_inherit() {
+    let alarm = AlarmFields {
+        time: Some("06:30:00".to_string()),
+        volume: Some("75".to_string()),
+        options: vec!["template=wake".to_string(), "fade=30".to_string()],
+        ..AlarmFields::default()
+    };
+    let template = AlarmFields {
+        time: Some("07:00:00".to_string()),
+        audio: Some("/a.ogg".to_string()),
+        options: vec!["fade=60".to_string(), "snooze=5".to_string()],
+        ..AlarmFields::default()
+    };
+
+    assert_eq!(alarm.inherit(&template), AlarmFields {
+        days: None,
+        time: Some("06:30:00".to_string()),
+        audio: Some("/a.ogg".to_string()),
+        volume: Some("75".to_string()),
+        options: vec!["template=wake".to_string(), "fade=30".to_string(), "snooze=5".to_string()]
+    });
+    assert_eq!(alarm.option("template"), Some("wake"));
+    assert_eq!(alarm.option("snooze"), None);
+}
+
+/// Alarm configuration
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct AlarmConfig {
     pub days: Vec<bool>,
     pub time: Time,
+    /// Empty for the reminders playing the chime chosen when they run
     pub audio_file: String,
     pub volume: u32,
-    pub last_run: DateTime<Local>
+    pub last_run: DateTime<Local>,
+    pub schedule: Schedule,
+    /// Time zone in which the days and time of the alarm are read
+    pub timezone: Zone,
+    /// Name of the alarm, also used to refer to it in commands
+    pub label: Option<String>,
+    /// Text shown when the alarm rings
+    pub message: Option<String>,
+    /// Minutes before a snoozed alarm rings again, 0 when it can not be snoozed
+    pub snooze: u32,
+    /// Seconds taken by the volume to rise from silence to the alarm volume
+    pub fade: u32,
+    /// Name of the audio output device, the default device when not set
+    pub device: Option<String>,
+    /// Set with `enabled=false`, the alarm is kept in the configuration but does not run
+    pub disabled: bool,
+    /// The alarm does not run before this day, see `Settings::paused_until`
+    pub paused_until: Option<NaiveDate>,
+    /// First day on which the alarm runs, set with `from=YYYY-MM-DD`
+    pub first_day: Option<NaiveDate>,
+    /// Last day on which the alarm runs, set with `to=YYYY-MM-DD`
+    pub last_day: Option<NaiveDate>,
+    /// Number of runs after which the alarm disables itself, set with `count=N`
+    pub count: Option<u32>,
+    /// Number of runs counted towards `count`
+    pub runs: u32,
+    /// Place where the alarm is defined
+    pub origin: Origin,
+    /// Skipped or moved next run
+    pub exception: Option<Exception>,
+    /// Starts computed for a day and the next one by `refresh`
+    pub resolved: Vec<(NaiveDate, NaiveDateTime)>
 }
 
 impl AlarmConfig {
 
-    /// Checks if the alarm has been launched today or before the app started
-    pub fn already_run_today(&self, now: &DateTime<Local>) -> bool {
-        self.last_run.day() == now.day() && {
-            hms_gte(
-                (self.last_run.hour(), self.last_run.minute(), self.last_run.second()),
-                (self.time.hours, self.time.minutes, self.time.seconds)
-            )
+    /// Computes the date and time at which the alarm runs for a given day,
+    /// the day before when the first event of a calendar starts less than `before` after midnight
+    fn resolve_start(&self, date: NaiveDate) -> NaiveDateTime {
+        let time = match self.schedule {
+            Schedule::Fixed | Schedule::Interval(_) => self.time.clone(),
+            Schedule::Calendar(ref rule) => {
+                match calendar::read_events(&rule.path) {
+                    Ok(events) => {
+                        match calendar::first_event_on(&events, date, rule.category.as_deref(), self.timezone) {
+                            Some((_, start)) => return start - chrono::Duration::seconds(rule.before as i64),
+                            None => self.time.clone()
+                        }
+                    },
+                    Err(err) => {
+                        calendar::report(&rule.path, &format!("Unable to read calendar {}: {}", rule.path, err));
+                        self.time.clone()
+                    }
+                }
+            },
+            Schedule::Solar(ref rule) => rule.time_on(date, self.timezone).unwrap_or_else(|| self.time.clone()),
+            Schedule::Random(ref until) => {
+                // seeded with the day and the alarm so that the time stays the same for the whole day
+                let mut hasher = DefaultHasher::new();
+                (date, &self.days, &self.time, until, &self.audio_file).hash(&mut hasher);
+                let mut rng = StdRng::seed_from_u64(hasher.finish());
+                Time::from_seconds(rng.random_range(self.time.to_seconds()..=until.to_seconds()))
+            }
+        };
+        time.on(date)
+    }
+
+    /// Date and time at which the alarm runs for a given day
+    pub fn start_on(&self, date: NaiveDate) -> NaiveDateTime {
+        match self.resolved.iter().find(|(day, _)| *day == date) {
+            Some(&(_, start)) => start,
+            None => self.resolve_start(date)
         }
     }
 
-    /// Checks if the current time is >= to the time set for the alarm to run
+    /// Time at which the alarm runs on a given day
+    pub fn time_on(&self, date: NaiveDate) -> Time {
+        let time = self.start_on(date).time();
+        Time { hours: time.hour(), minutes: time.minute(), seconds: time.second() }
+    }
+
+    /// Computes and keeps the alarm time for a given day and the next one, once per day
+    pub fn refresh(&mut self, date: NaiveDate) {
+        let up_to_date = self.resolved.first().is_some_and(|&(day, _)| day == date);
+        if !up_to_date {
+            self.resolved = [Some(date), date.succ_opt()].iter().flatten()
+                .map(|&day| (day, self.resolve_start(day)))
+                .collect();
+        }
+    }
+
+    /// Date and time of an instant in the time zone of the alarm
+    pub fn local_time(&self, now: &DateTime<Local>) -> NaiveDateTime {
+        self.timezone.naive(now)
+    }
+
+    /// Checks if the alarm is a reminder, running at an interval
+    pub fn is_reminder(&self) -> bool {
+        matches!(self.schedule, Schedule::Interval(_))
+    }
+
+    /// Times at which the alarm runs on a given day, every interval of the window for reminders
+    pub fn times_on(&self, date: NaiveDate) -> Vec<Time> {
+        match self.schedule {
+            Schedule::Interval(ref rule) => (self.time.to_seconds()..=rule.until.to_seconds())
+                .step_by(rule.every as usize)
+                .map(Time::from_seconds)
+                .collect(),
+            _ => vec![self.time_on(date)]
+        }
+    }
+
+    /// Instants at which the alarm runs on a given day of its time zone
+    /// See `Zone::resolve` for the days of daylight saving time transitions
+    pub fn occurrences_on(&self, date: NaiveDate) -> Vec<DateTime<Local>> {
+        let starts = match self.schedule {
+            Schedule::Interval(_) => self.times_on(date).iter().map(|time| time.on(date)).collect(),
+            _ => vec![self.start_on(date)]
+        };
+        let mut runs: Vec<DateTime<Local>> = starts.iter()
+            .filter_map(|naive| self.timezone.resolve(naive))
+            .collect();
+        // times skipped by clocks going forward all run at the end of the gap
+        runs.dedup();
+        runs
+    }
+
+    /// Instants at which the alarm runs on a given day once its exception is applied, in time order,
+    /// none when paused, out of the days of the alarm or after its last counted run
+    pub fn planned_runs(&self, date: NaiveDate) -> Vec<DateTime<Local>> {
+        if self.remaining_runs() == Some(0)
+            || self.paused_until.is_some_and(|until| date < until)
+            || self.first_day.is_some_and(|first| date < first)
+            || self.last_day.is_some_and(|last| date > last) {
+            return vec![];
+        }
+        let mut runs: Vec<DateTime<Local>> = self.occurrences_on(date).into_iter()
+            .filter_map(|run| match self.exception {
+                Some(ref exception) if exception.run == run => exception.moved_to,
+                _ => Some(run)
+            })
+            .collect();
+        runs.sort();
+        runs
+    }
+
+    /// Checks if all the runs of the day have been launched, or were before the app started
+    pub fn already_run_today(&self, now: &DateTime<Local>) -> bool {
+        self.planned_runs(self.local_time(now).date()).iter().all(|run| self.last_run >= *run)
+    }
+
+    /// Checks if the current time is >= to the first time set for the alarm to run today
     pub fn is_expired(&self, now: &DateTime<Local>) -> bool {
-        hms_gte(
-            (now.hour(), now.minute(), now.second()),
-            (self.time.hours, self.time.minutes, self.time.seconds)
-        )
+        self.planned_runs(self.local_time(now).date()).iter().any(|run| *now >= *run)
+    }
+
+    /// Checks if a run of an active day has come since the last run,
+    /// the run of tomorrow being due today when it moves to the day before
+    pub fn is_due(&self, now: &DateTime<Local>) -> bool {
+        let today = self.local_time(now).date();
+        [Some(today), today.succ_opt()].iter()
+            .flatten()
+            .filter(|date| self.is_active_weekday(date.weekday()))
+            .flat_map(|date| self.planned_runs(*date))
+            .any(|run| *now >= run && run > self.last_run)
     }
 
     /// Checks if the current day is configured to run the alarm
     pub fn is_active_day(&self, now: &DateTime<Local>) -> bool {
-        let day_index = (now.weekday().number_from_monday() - 1) as usize;
+        self.is_active_weekday(self.local_time(now).weekday())
+    }
+
+    /// Checks if the alarm is configured to run on a day of the week
+    pub fn is_active_weekday(&self, weekday: Weekday) -> bool {
+        let day_index = (weekday.number_from_monday() - 1) as usize;
         *self.days.get(day_index).unwrap_or(&false)
     }
 
+    /// Number of runs left before the alarm disables itself, None when it has no count
+    pub fn remaining_runs(&self) -> Option<u32> {
+        self.count.map(|count| count.saturating_sub(self.runs))
+    }
+
+    /// Counts a run, disables the alarm when it has run `count` times
+    pub fn count_run(&mut self) {
+        self.runs += 1;
+        if self.remaining_runs() == Some(0) {
+            self.disabled = true;
+        }
+    }
+
+    /// Next time the alarm will run, looking one week ahead
+    pub fn next_run(&self, now: &DateTime<Local>) -> Option<DateTime<Local>> {
+        if self.disabled {
+            return None;
+        }
+        let today = self.local_time(now).date();
+        (0..8)
+            .filter_map(|offset| today.checked_add_days(chrono::Days::new(offset)))
+            .filter(|date| self.is_active_weekday(date.weekday()))
+            .flat_map(|date| self.planned_runs(date))
+            .find(|run| *run > *now && *run > self.last_run)
+    }
+
+    /// Exception skipping the next run of the alarm, or moving it to another time of the same day
+    pub fn change_next_run(&self, now: &DateTime<Local>, time: Option<&str>) -> Result<Exception, String> {
+        let next = self.next_run(now).ok_or("The alarm does not run in the coming week")?;
+        // the planned run stays the reference when a moved run is changed again
+        let run = match self.exception {
+            Some(ref exception) if exception.moved_to == Some(next) => exception.run,
+            _ => next
+        };
+
+        let moved_to = match time {
+            Some(time) => {
+                let time = parse_time(time)?;
+                let date = self.local_time(&run).date();
+                let moved_to = date.and_hms_opt(time.hours, time.minutes, time.seconds)
+                    .and_then(|naive| self.timezone.resolve(&naive))
+                    .ok_or(format!("Invalid time on {}", date))?;
+                if moved_to <= *now {
+                    return Err(format!("{} has already passed", moved_to.to_rfc3339()));
+                }
+                Some(moved_to)
+            },
+            None => None
+        };
+        Ok(Exception { run, moved_to })
+    }
+
+    /// Drops the exception once both the planned run and the moved run have passed
+    /// The planned run then counts as run so that a run moved earlier does not also ring at its planned time
+    pub fn expire_exception(&mut self, now: &DateTime<Local>) -> bool {
+        let passed = match self.exception {
+            Some(ref exception) => {
+                let last = cmp::max(exception.run, exception.moved_to.unwrap_or(exception.run));
+                if *now > last { Some(last) } else { None }
+            },
+            None => None
+        };
+        match passed {
+            Some(last) => {
+                self.last_run = cmp::max(self.last_run, last);
+                self.exception = None;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Key under which the state of the alarm is kept: its label, or the file where it is defined with a digest of its definition,
+    /// which does not change when lines are added or removed around it
+    /// Enabling or disabling the alarm keeps its key, identical alarms of a file without label share it
+    pub fn key(&self) -> String {
+        match self.label {
+            Some(ref label) => label.clone(),
+            None => {
+                let definition = format!("{:?};{};{};{};{:?};{:?};{:?};{};{};{:?};{:?};{:?};{:?}",
+                    self.days, self.time, self.audio_file, self.volume, self.schedule, self.timezone,
+                    self.message, self.snooze, self.fade, self.device, self.first_day, self.last_day, self.count);
+                let file = self.origin.file.canonicalize().unwrap_or_else(|_| self.origin.file.clone());
+                format!("{}#{:016x}", file.display(), digest(&definition))
+            }
+        }
+    }
+
+    /// Times at which the alarm runs after `from`, up to `to` included
+    pub fn occurrences(&self, from: &DateTime<Local>, to: &DateTime<Local>) -> Vec<DateTime<Local>> {
+        if self.disabled {
+            return vec![];
+        }
+        let first = self.local_time(from).date();
+        let last = self.local_time(to).date();
+        first.iter_days()
+            .take_while(|date| *date <= last)
+            .filter(|date| self.is_active_weekday(date.weekday()))
+            .flat_map(|date| self.planned_runs(date))
+            .filter(|run| run > from && run <= to)
+            .take(self.remaining_runs().map_or(usize::MAX, |runs| runs as usize))
+            .collect()
+    }
+
+    /// Days of the alarm written as a mask, such as `MTWTF__`
+    pub fn days_mask(&self) -> String {
+        "MTWTFSS".chars()
+            .enumerate()
+            .map(|(index, day)| if *self.days.get(index).unwrap_or(&false) { day } else { '_' })
+            .collect()
+    }
+
+    /// Describes how the alarm time is computed
+    fn describe_schedule(&self) -> String {
+        match self.schedule {
+            Schedule::Fixed => String::new(),
+            Schedule::Calendar(ref rule) => format!(" ({} before first event{} in {}, default {})",
+                Time::from_seconds(rule.before),
+                rule.category.as_ref().map(|c| format!(" of category {}", c)).unwrap_or_default(),
+                rule.path,
+                self.time
+            ),
+            Schedule::Solar(ref rule) => format!(" ({}{}{})",
+                rule.event.as_str(),
+                if rule.offset < 0 { "-" } else { "+" },
+                Time::from_seconds(rule.offset.unsigned_abs())
+            ),
+            Schedule::Random(ref until) => format!(" (random between {} and {})", self.time, until),
+            Schedule::Interval(ref rule) => format!(" (every {} until {})", Time::from_seconds(rule.every), rule.until)
+        }
+    }
+
+    /// Framed label and message, shown when the alarm rings
+    pub fn banner(&self) -> Option<String> {
+        let lines: Vec<&str> = self.label.iter().chain(self.message.iter()).map(|s| s.as_str()).collect();
+        if lines.is_empty() {
+            return None;
+        }
+
+        let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let border = "*".repeat(width + 4);
+        let framed: Vec<String> = lines.iter().map(|line| format!("* {:<width$} *", line, width = width)).collect();
+
+        Some(format!("{}\n{}\n{}", border, framed.join("\n"), border))
+    }
+
     /// Format the output string
     pub fn pretty_print(&self, now: &DateTime<Local>) {
 
@@ -59,30 +601,64 @@ impl AlarmConfig {
             }
         }
 
-        println!("Days: {:?}, Time: {:#02}:{:#02}:{:#02}, File: {}, Volume: {}%, Last run: {}, Run today: {}",
+        let zone = match self.timezone {
+            Zone::Local => String::new(),
+            Zone::Named(_) => format!(" {}", self.timezone)
+        };
+
+        let label = match self.label {
+            Some(ref label) => format!("[{}] ", label),
+            None => String::new()
+        };
+
+        let fade = if self.fade > 0 { format!(" (fade {}s)", self.fade) } else { String::new() };
+
+        let state = if self.disabled { "DISABLED " } else { "" };
+
+        let device = match self.device {
+            Some(ref device) => format!(", Device: {}", device),
+            None => String::new()
+        };
+
+        let days_range = match (self.first_day, self.last_day) {
+            (Some(first), Some(last)) => format!(", From {} to {}", first, last),
+            (Some(first), None) => format!(", From {}", first),
+            (None, Some(last)) => format!(", To {}", last),
+            (None, None) => String::new()
+        };
+
+        let count = match self.count {
+            Some(count) => format!(", Runs: {} of {}", self.runs, count),
+            None => String::new()
+        };
+
+        let exception = match self.exception {
+            Some(Exception { ref run, moved_to: Some(ref moved_to) }) => format!(", Next run moved from {} to {}", run.to_rfc3339(), moved_to.to_rfc3339()),
+            Some(Exception { ref run, moved_to: None }) => format!(", Next run skipped: {}", run.to_rfc3339()),
+            None => String::new()
+        };
+
+        println!("{}{}Days: {:?}{}, Time: {}{}{}, File: {}, Volume: {}%{}{}, Snooze: {} min{}, Last run: {}, Run today: {}{}",
+                 state,
+                 label,
                  days_selection,
-                 self.time.hours, self.time.minutes, self.time.seconds,
-                 self.audio_file,
+                 days_range,
+                 self.time_on(self.local_time(now).date()),
+                 zone,
+                 self.describe_schedule(),
+                 if self.audio_file.is_empty() { "chime" } else { &self.audio_file },
                  self.volume,
+                 fade,
+                 device,
+                 self.snooze,
+                 count,
                  self.last_run.to_rfc3339(),
-                 !self.already_run_today(now)
+                 !self.disabled && !self.already_run_today(now),
+                 exception
         )
     }
 }
 
-/// Compares hours, minutes, seconnds for 2 times
-fn hms_gte(hms1 : (u32, u32, u32), hms2: (u32, u32, u32)) -> bool {
-    hms1.0 * 3600 + hms1.1 * 60 + hms1.2 >= hms2.0 * 3600 + hms2.1 * 60 + hms2.2
-}
-
-#[test]
-fn test_hms_gte() {
-    assert!(hms_gte((0,0,0), (0,0,0)));
-    assert!(hms_gte((8,0,0), (7,59,59)));
-    assert!(hms_gte((23,0,0), (0,59,59)));
-    assert!(hms_gte((0,1,0), (0,0,59)));
-}
-
 /// Parses a string to an unsigned int given a min and a max
 /// max is default value in case of parse failure
 fn parse_int_with_min_max(i: &str, min: u32, max: u32) -> u32 {
@@ -99,143 +675,1091 @@ fn test_parse_int_with_min_max() {
     assert_eq!(parse_int_with_min_max(&"80".to_string(),10,100), 80)
 }
 
+/// Names of the days of the week, monday first
+const DAY_NAMES: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+/// Finds a day of the week from its name or the start of its name, monday being 0
+fn parse_day_name(s: &str) -> Result<usize, String> {
+    let lowercase = s.trim().to_lowercase();
+    let matching: Vec<usize> = (0..7)
+        .filter(|&day| !lowercase.is_empty() && DAY_NAMES[day].starts_with(&lowercase))
+        .collect();
+
+    match matching.as_slice() {
+        [day] => Ok(*day),
+        [] => Err(format!("Unknown day: {}", s.trim())),
+        _ => Err(format!("Ambiguous day {}: {}", s.trim(), matching.iter().map(|&day| DAY_NAMES[day]).collect::<Vec<_>>().join(" or ")))
+    }
+}
+
+#[test]
+fn test_parse_day_name() {
+    assert_eq!(parse_day_name("Mon"), Ok(0));
+    assert_eq!(parse_day_name("thursday"), Ok(3));
+    assert_eq!(parse_day_name("Th"), Ok(3));
+    assert_eq!(parse_day_name("T"), Err("Ambiguous day T: tuesday or thursday".to_string()));
+    assert_eq!(parse_day_name("Mond"), Ok(0));
+    assert_eq!(parse_day_name("Monday2"), Err("Unknown day: Monday2".to_string()));
+    assert!(parse_day_name("").is_err());
+}
+
 /// Parses a string to a vec of 7 days, monday first
 /// For each position true when day is active, false otherwise
-fn parse_days(s: &str) -> Option<Vec<bool>> {
-    match s.to_string().as_bytes() {
-        [mon, tue, wed, fri, thu, sat, sun] => {
-            Some(vec![
-                *mon == ('M' as u8),
-                *tue == ('T' as u8),
-                *wed == ('W' as u8),
-                *fri == ('T' as u8),
-                *thu == ('F' as u8),
-                *sat == ('S' as u8),
-                *sun == ('S' as u8)
-            ])
-        },
-        _ => None
+/// Days are written `daily`, `weekdays`, `weekends`, as a list of days and ranges such as `Mon-Wed,Fri`,
+/// or as a mask of 7 characters such as `MTWTF__`
+fn parse_days(s: &str) -> Result<Vec<bool>, String> {
+    let lowercase = s.trim().to_lowercase();
+    match lowercase.as_str() {
+        "daily" => return Ok(vec![true; 7]),
+        "weekdays" => return Ok(vec![true, true, true, true, true, false, false]),
+        "weekends" => return Ok(vec![false, false, false, false, false, true, true]),
+        _ => {}
+    }
+
+    // words that can not be a mask are read as day names
+    let is_day_list = lowercase.contains(',') || lowercase.contains('-') || (lowercase.chars().all(|c| c.is_ascii_alphabetic()) && lowercase.len() != 7);
+    if is_day_list || DAY_NAMES.iter().any(|name| name.starts_with(&lowercase) && !lowercase.is_empty()) {
+        let mut days = vec![false; 7];
+        for item in s.trim().split(',') {
+            match item.split_once('-') {
+                Some((first, last)) => {
+                    // ranges may go over the end of the week, such as Fri-Mon
+                    let (mut day, last) = (parse_day_name(first)?, parse_day_name(last)?);
+                    days[day] = true;
+                    while day != last {
+                        day = (day + 1) % 7;
+                        days[day] = true;
+                    }
+                },
+                None => days[parse_day_name(item)?] = true
+            }
+        }
+        return Ok(days);
     }
+
+    // a letter of the mask is either the letter of its day, meaning active, or an error
+    let letters = "MTWTFSS".chars();
+    let mask: Vec<char> = s.trim().chars().collect();
+    if mask.len() != 7 {
+        return Err(format!("Invalid days: {}", s));
+    }
+    mask.iter().zip(letters).enumerate()
+        .map(|(day, (c, letter))| match *c {
+            c if c.eq_ignore_ascii_case(&letter) => Ok(true),
+            c if c.is_alphabetic() => Err(format!("Invalid days {}: {} is not the letter of {}", s, c, DAY_NAMES[day])),
+            _ => Ok(false)
+        })
+        .collect()
 }
 
 #[test]
 fn test_parse_days() {
-    assert_eq!(parse_days(&"MTWTFSS".to_string()), Some(vec![true,true,true,true,true,true,true]));
-    assert_eq!(parse_days(&"M_W_F_S".to_string()), Some(vec![true,false,true,false,true,false,true]));
-    assert_eq!(parse_days(&"_______".to_string()), Some(vec![false,false,false,false,false,false,false]));
-    assert_eq!(parse_days(&"???????".to_string()), Some(vec![false,false,false,false,false,false,false]));
-    assert_eq!(parse_days(&"BAD_LENGTH".to_string()), None);
-    assert_eq!(parse_days(&"SMALL".to_string()), None);
-}
-
-/// Parses a time to hours, minutes, seconds
-fn parse_time(s: &str) -> Option<Time> {
-    let x = s.split(":");
-    let split: Vec<&str> = x.collect();
-    match split.as_slice() {
-        [h, m, s] => {
-            Some(Time {
-                hours: parse_int_with_min_max(h, 0, 23),
-                minutes: parse_int_with_min_max(m, 0, 59),
-                seconds: parse_int_with_min_max(s, 0, 59)
-            })
-        },
-        _ => None
+    assert_eq!(parse_days(&"MTWTFSS".to_string()), Ok(vec![true,true,true,true,true,true,true]));
+    assert_eq!(parse_days(&"M_W_F_S".to_string()), Ok(vec![true,false,true,false,true,false,true]));
+    assert_eq!(parse_days(&"_______".to_string()), Ok(vec![false,false,false,false,false,false,false]));
+    assert_eq!(parse_days(&"???????".to_string()), Ok(vec![false,false,false,false,false,false,false]));
+    assert!(parse_days(&"BAD_LENGTH".to_string()).is_err());
+    assert!(parse_days(&"SMALL".to_string()).is_err());
+
+    assert_eq!(parse_days("daily"), Ok(vec![true; 7]));
+    assert_eq!(parse_days("Weekdays"), Ok(vec![true,true,true,true,true,false,false]));
+    assert_eq!(parse_days("weekends"), Ok(vec![false,false,false,false,false,true,true]));
+    assert_eq!(parse_days("Mon-Fri"), Ok(vec![true,true,true,true,true,false,false]));
+    assert_eq!(parse_days("Mon,Wed,Fri"), Ok(vec![true,false,true,false,true,false,false]));
+    assert_eq!(parse_days("Fri-Mon"), Ok(vec![true,false,false,false,true,true,true]));
+    assert_eq!(parse_days("tuesday"), Ok(vec![false,true,false,false,false,false,false]));
+    assert_eq!(parse_days("Mon, Thu-Sat"), Ok(vec![true,false,false,true,true,true,false]));
+    assert_eq!(parse_days("S"), Err("Ambiguous day S: saturday or sunday".to_string()));
+    assert_eq!(parse_days("weekday"), Err("Invalid days weekday: w is not the letter of monday".to_string()));
+    assert!(parse_days("Mon,,Fri").is_err());
+    assert!(parse_days("Mon-").is_err());
+}
+
+/// Parses a time written `HH:MM:SS`, `HH:MM`, `H:MM`, `18h30`, or on a 12-hour clock such as `6:30am` or `6pm`
+/// Hours, minutes and seconds of the `HH:MM:SS` format are capped to their maximum
+fn parse_time(s: &str) -> Result<Time, String> {
+    let invalid = || format!("Invalid time: {}", s);
+    let lowercase = s.trim().to_lowercase();
+
+    // true in the afternoon on a 12-hour clock
+    let (clock, afternoon) = if let Some(rest) = lowercase.strip_suffix("am") {
+        (rest.trim_end(), Some(false))
+    } else if let Some(rest) = lowercase.strip_suffix("pm") {
+        (rest.trim_end(), Some(true))
+    } else {
+        (lowercase.as_str(), None)
+    };
+
+    let split: Vec<&str> = match clock.split_once('h') {
+        Some((h, "")) if afternoon.is_none() => vec![h, "00"],
+        Some((h, m)) if afternoon.is_none() => vec![h, m],
+        Some(_) => return Err(invalid()),
+        None => clock.split(':').collect()
+    };
+
+    let is_number = |part: &str, max_length: usize| !part.is_empty() && part.len() <= max_length && part.chars().all(|c| c.is_ascii_digit());
+    let (hours, minutes, seconds) = match split.as_slice() {
+        [h, m, s] if afternoon.is_none() && is_number(h, 2) && is_number(m, 2) && is_number(s, 2) => return Ok(Time {
+            hours: parse_int_with_min_max(h, 0, 23),
+            minutes: parse_int_with_min_max(m, 0, 59),
+            seconds: parse_int_with_min_max(s, 0, 59)
+        }),
+        [h, m, s] if is_number(h, 2) && m.len() == 2 && is_number(m, 2) && s.len() == 2 && is_number(s, 2) => (h, *m, *s),
+        [h, m] if is_number(h, 2) && m.len() == 2 && is_number(m, 2) => (h, *m, "0"),
+        [h] if afternoon.is_some() && is_number(h, 2) => (h, "0", "0"),
+        [h] if is_number(h, 4) => return Err(format!("Ambiguous time {}: write it HH:MM, or with am or pm", s.trim())),
+        _ => return Err(invalid())
+    };
+
+    let (mut hours, minutes, seconds): (u32, u32, u32) = (hours.parse().unwrap_or(0), minutes.parse().unwrap_or(0), seconds.parse().unwrap_or(0));
+    if minutes > 59 || seconds > 59 {
+        return Err(invalid());
+    }
+    match afternoon {
+        Some(_) if hours == 0 || hours > 12 => return Err(format!("Invalid time {}: hours go from 1 to 12 with am or pm", s.trim())),
+        Some(pm) => hours = hours % 12 + if pm { 12 } else { 0 },
+        None if hours > 23 => return Err(invalid()),
+        None => {}
     }
+
+    Ok(Time { hours, minutes, seconds })
 }
 
 #[test]
 fn test_parse_time() {
-    assert_eq!(parse_time("00:00:00"), Some(Time { hours: 0, minutes: 0, seconds: 0 }));
-    assert_eq!(parse_time("01:02:03"), Some(Time { hours: 1, minutes: 2, seconds: 3 }));
-    assert_eq!(parse_time("23:59:59"), Some(Time { hours: 23, minutes: 59, seconds: 59 }));
-    assert_eq!(parse_time("24:00:00"), Some(Time { hours: 23, minutes: 0, seconds: 0 })); // max is 23
-    assert_eq!(parse_time("00:60:00"), Some(Time { hours: 0, minutes: 59, seconds: 0 })); // max is 59
-    assert_eq!(parse_time("00:00:60"), Some(Time { hours: 0, minutes: 0, seconds: 59 })); // max is 59
-    assert_eq!(parse_time("????????"), None);
+    assert_eq!(parse_time("00:00:00"), Ok(Time { hours: 0, minutes: 0, seconds: 0 }));
+    assert_eq!(parse_time("01:02:03"), Ok(Time { hours: 1, minutes: 2, seconds: 3 }));
+    assert_eq!(parse_time("23:59:59"), Ok(Time { hours: 23, minutes: 59, seconds: 59 }));
+    assert_eq!(parse_time("24:00:00"), Ok(Time { hours: 23, minutes: 0, seconds: 0 })); // max is 23
+    assert_eq!(parse_time("00:60:00"), Ok(Time { hours: 0, minutes: 59, seconds: 0 })); // max is 59
+    assert_eq!(parse_time("00:00:60"), Ok(Time { hours: 0, minutes: 0, seconds: 59 })); // max is 59
+    assert!(parse_time("????????").is_err());
+
+    let at = |hours: u32, minutes: u32| Ok(Time { hours, minutes, seconds: 0 });
+    assert_eq!(parse_time("6:30"), at(6, 30));
+    assert_eq!(parse_time("06:30"), at(6, 30));
+    assert_eq!(parse_time("6:30am"), at(6, 30));
+    assert_eq!(parse_time("6:30 PM"), at(18, 30));
+    assert_eq!(parse_time("12am"), at(0, 0));
+    assert_eq!(parse_time("12:15pm"), at(12, 15));
+    assert_eq!(parse_time("18h30"), at(18, 30));
+    assert_eq!(parse_time("7h"), at(7, 0));
+    assert_eq!(parse_time("630"), Err("Ambiguous time 630: write it HH:MM, or with am or pm".to_string()));
+    assert_eq!(parse_time("6"), Err("Ambiguous time 6: write it HH:MM, or with am or pm".to_string()));
+    assert_eq!(parse_time("13:00pm"), Err("Invalid time 13:00pm: hours go from 1 to 12 with am or pm".to_string()));
+    assert!(parse_time("24:00").is_err());
+    assert!(parse_time("6:3").is_err());
+    assert!(parse_time("6:75").is_err());
+    assert!(parse_time("18h30pm").is_err());
+    assert!(parse_time("noon").is_err());
 }
 
-/// Parses a string line to an alarm config
-fn parse_configuration<'a>(s: &'a str) -> Option<AlarmConfig> {
-    let x = s.split(";");
-    let split: Vec<&str> = x.collect();
-    let last_run = chrono::Local::now();
-
-    let maybe_config: Option<AlarmConfig> = match split.as_slice() {
-        [the_days, the_time, the_audio, the_volume] => {
-            let time_opt = parse_time(the_time);
-
-            let selected_days_opt: Option<Vec<bool>> = parse_days(the_days);
-            let volume = parse_int_with_min_max(the_volume,0 ,100);
-            match (selected_days_opt, time_opt) {
-                (Some(selected_days), Some(time)) => {
-                    let cfg = AlarmConfig {
-                        days: selected_days,
-                        time,
-                        audio_file: the_audio.to_string(),
-                        volume,
-                        last_run
-                    };
-                    Some(cfg)
-                },
-                _ => {
-                    println!("Days or time invalid");
-                    None
+/// Optional `key=value` fields of an alarm line
+#[derive(Debug, Clone, PartialEq, Default)]
+struct AlarmOptions {
+    schedule: Schedule,
+    timezone: Option<Zone>,
+    label: Option<String>,
+    message: Option<String>,
+    snooze: Option<u32>,
+    fade: Option<u32>,
+    device: Option<String>,
+    enabled: Option<bool>,
+    first_day: Option<NaiveDate>,
+    last_day: Option<NaiveDate>,
+    count: Option<u32>,
+    /// Seconds between the runs of a reminder
+    every: Option<u32>
+}
+
+/// Parses a day written `YYYY-MM-DD`
+pub fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").map_err(|_| format!("Invalid date, expected YYYY-MM-DD: {}", s))
+}
+
+/// Parses the optional `key=value` fields following the volume
+fn parse_options(options: &[&str]) -> Result<AlarmOptions, String> {
+    let mut calendar_path: Option<String> = None;
+    let mut category: Option<String> = None;
+    let mut before: Option<u32> = None;
+    let mut timezone: Option<Zone> = None;
+    let mut label: Option<String> = None;
+    let mut message: Option<String> = None;
+    let mut snooze: Option<u32> = None;
+    let mut fade: Option<u32> = None;
+    let mut device: Option<String> = None;
+    let mut enabled: Option<bool> = None;
+    let mut first_day: Option<NaiveDate> = None;
+    let mut last_day: Option<NaiveDate> = None;
+    let mut count: Option<u32> = None;
+    let mut every: Option<u32> = None;
+
+    for option in options {
+        match option.split_once('=') {
+            Some(("calendar", value)) => calendar_path = Some(value.to_string()),
+            Some(("category", value)) => category = Some(value.to_string()),
+            Some(("before", value)) => {
+                let duration = parse_time(value).map_err(|_| format!("Invalid duration: {}", value))?;
+                before = Some(duration.to_seconds());
+            },
+            Some(("timezone", value)) => timezone = Some(Zone::parse(value)?),
+            Some(("label", value)) => {
+                // numbers identify alarms by position
+                if value.is_empty() || value.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(format!("Invalid label: {}", value));
                 }
-            }
+                label = Some(value.to_string());
+            },
+            Some(("message", value)) => message = Some(value.to_string()),
+            Some(("snooze", value)) => snooze = Some(value.parse().map_err(|_| format!("Invalid snooze minutes: {}", value))?),
+            Some(("fade", value)) => fade = Some(value.parse().map_err(|_| format!("Invalid fade seconds: {}", value))?),
+            Some(("device", value)) if !value.is_empty() => device = Some(value.to_string()),
+            Some(("enabled", value)) => enabled = Some(value.parse().map_err(|_| format!("Invalid enabled, expected true or false: {}", value))?),
+            Some(("from", value)) => first_day = Some(parse_date(value)?),
+            Some(("to", value)) => last_day = Some(parse_date(value)?),
+            Some(("count", value)) => match value.parse::<u32>() {
+                Ok(runs) if runs > 0 => count = Some(runs),
+                _ => return Err(format!("Invalid count, expected a number of runs: {}", value))
+            },
+            Some(("every", value)) => every = Some(u32::try_from(timer::parse_duration(value)?.num_seconds()).ok()
+                .filter(|seconds| *seconds > 0)
+                .ok_or(format!("Invalid every, expected a duration from 1s to {}h: {}", u32::MAX / 3600, value))?),
+            _ => return Err(format!("Unknown option: {}", option))
+        }
+    }
+
+    if let (Some(first), Some(last)) = (first_day, last_day) {
+        if first > last {
+            return Err(format!("The alarm ends on {} before it starts on {}", last, first));
+        }
+    }
+
+    let schedule = match calendar_path {
+        Some(path) => Schedule::Calendar(CalendarRule {
+            path,
+            category,
+            before: before.unwrap_or(0)
+        }),
+        None if category.is_some() || before.is_some() => return Err("Options category and before require a calendar".to_string()),
+        None => Schedule::Fixed
+    };
+
+    Ok(AlarmOptions { schedule, timezone, label, message, snooze, fade, device, enabled, first_day, last_day, count, every })
+}
+
+#[test]
+fn test_parse_options() {
+    assert_eq!(parse_options(&[]), Ok(AlarmOptions::default()));
+    assert_eq!(parse_options(&["calendar=/home/me/oncall.ics", "before=01:30:00", "category=On-call"]).unwrap().schedule, Schedule::Calendar(CalendarRule {
+        path: "/home/me/oncall.ics".to_string(),
+        category: Some("On-call".to_string()),
+        before: 5400
+    }));
+    assert_eq!(parse_options(&["timezone=Asia/Tokyo"]).unwrap().timezone, Some(Zone::Named(chrono_tz::Asia::Tokyo)));
+    assert!(parse_options(&["timezone=Asia/Atlantis"]).is_err());
+    let labelled = parse_options(&["label=Wake up", "message=Stand-up at 9"]).unwrap();
+    assert_eq!(labelled.label, Some("Wake up".to_string()));
+    assert_eq!(labelled.message, Some("Stand-up at 9".to_string()));
+    assert!(parse_options(&["label=2"]).is_err());
+    let sound = parse_options(&["snooze=5", "fade=30", "device=USB Speaker"]).unwrap();
+    assert_eq!((sound.snooze, sound.fade, sound.device), (Some(5), Some(30), Some("USB Speaker".to_string())));
+    assert!(parse_options(&["snooze=later"]).is_err());
+    assert!(parse_options(&["device="]).is_err());
+    assert_eq!(parse_options(&["enabled=false"]).unwrap().enabled, Some(false));
+    assert!(parse_options(&["enabled=no"]).is_err());
+    let term = parse_options(&["from=2026-09-01", "to=2027-07-03", "count=3"]).unwrap();
+    assert_eq!((term.first_day, term.last_day, term.count), (NaiveDate::from_ymd_opt(2026, 9, 1), NaiveDate::from_ymd_opt(2027, 7, 3), Some(3)));
+    assert!(parse_options(&["from=2027-07-03", "to=2026-09-01"]).is_err());
+    assert!(parse_options(&["to=tomorrow"]).is_err());
+    assert!(parse_options(&["count=0"]).is_err());
+    assert!(parse_options(&["before=01:30:00"]).is_err());
+    assert!(parse_options(&["calendar=/a.ics", "before=soon"]).is_err());
+    assert!(parse_options(&["unknown"]).is_err());
+}
+
+/// Parses an offset written `HH:MM` or `HH:MM:SS` to a number of seconds
+fn parse_offset(s: &str) -> Option<u32> {
+    let parts: Option<Vec<u32>> = s.split(':').map(|part| part.parse().ok()).collect();
+    match parts?.as_slice() {
+        [h, m] if *m < 60 => Some(h * 3600 + m * 60),
+        [h, m, s] if *m < 60 && *s < 60 => Some(h * 3600 + m * 60 + s),
+        _ => None
+    }
+}
+
+#[test]
+fn test_parse_offset() {
+    assert_eq!(parse_offset("00:20"), Some(1200));
+    assert_eq!(parse_offset("01:00:30"), Some(3630));
+    assert_eq!(parse_offset("00:60"), None);
+    assert_eq!(parse_offset("20"), None);
+    assert_eq!(parse_offset("-00:20"), None);
+}
+
+/// Parses the time field: a time, a sun event with an optional offset such as `sunrise-00:20`,
+/// or a window such as `07:00:00..07:10:00`
+/// Sun events come with the time used when the sun does not rise or set
+fn parse_time_field(s: &str, settings: &Settings) -> Result<(Time, Option<Schedule>), String> {
+    let sun_events = [(SolarEvent::Sunrise, 6), (SolarEvent::Sunset, 18)];
+
+    for &(event, default_hours) in sun_events.iter() {
+        if let Some(offset_str) = s.strip_prefix(event.as_str()) {
+            let offset = match offset_str.chars().next() {
+                None => 0,
+                Some(sign) if sign == '-' || sign == '+' => {
+                    let seconds = parse_offset(&offset_str[1..]).ok_or(format!("Invalid offset: {}", offset_str))? as i32;
+                    if sign == '-' { -seconds } else { seconds }
+                },
+                Some(_) => return Err(format!("Invalid time: {}", s))
+            };
+
+            return match (settings.latitude, settings.longitude) {
+                (Some(latitude), Some(longitude)) => Ok((
+                    Time { hours: default_hours, minutes: 0, seconds: 0 },
+                    Some(Schedule::Solar(SolarRule { event, offset, latitude, longitude }))
+                )),
+                _ => Err(format!("{} requires the latitude and longitude settings", event.as_str()))
+            };
+        }
+    }
+
+    if let Some((from, until)) = s.split_once("..") {
+        let (from, until) = (parse_time(from)?, parse_time(until)?);
+        return if from.to_seconds() <= until.to_seconds() {
+            Ok((from, Some(Schedule::Random(until))))
+        } else {
+            Err(format!("Invalid time window: {}", s))
+        };
+    }
+
+    parse_time(s).map(|time| (time, None))
+}
+
+#[test]
+fn test_parse_time_field() {
+    let settings = Settings { latitude: Some(48.85), longitude: Some(2.35), ..Settings::default() };
+
+    assert_eq!(parse_time_field("06:30:00", &settings), Ok((Time { hours: 6, minutes: 30, seconds: 0 }, None)));
+    assert_eq!(parse_time_field("sunrise-00:20", &settings), Ok((Time { hours: 6, minutes: 0, seconds: 0 }, Some(Schedule::Solar(SolarRule {
+        event: SolarEvent::Sunrise, offset: -1200, latitude: 48.85, longitude: 2.35
+    })))));
+    assert_eq!(parse_time_field("sunset+00:10", &settings), Ok((Time { hours: 18, minutes: 0, seconds: 0 }, Some(Schedule::Solar(SolarRule {
+        event: SolarEvent::Sunset, offset: 600, latitude: 48.85, longitude: 2.35
+    })))));
+    assert_eq!(parse_time_field("sunset", &settings).unwrap().1, Some(Schedule::Solar(SolarRule {
+        event: SolarEvent::Sunset, offset: 0, latitude: 48.85, longitude: 2.35
+    })));
+    assert_eq!(parse_time_field("07:00:00..07:10:00", &settings), Ok((Time { hours: 7, minutes: 0, seconds: 0 }, Some(Schedule::Random(
+        Time { hours: 7, minutes: 10, seconds: 0 }
+    )))));
+    assert!(parse_time_field("07:10:00..07:00:00", &settings).is_err());
+    assert!(parse_time_field("07:00:00..", &settings).is_err());
+    assert!(parse_time_field("sunrise-soon", &settings).is_err());
+    assert!(parse_time_field("sunrises", &settings).is_err());
+    assert!(parse_time_field("sunrise", &Settings::default()).is_err());
+    assert!(parse_time_field("noon", &settings).is_err());
+}
+
+/// Parses a `key=value` line to a setting shared by all the alarms
+/// `audio`, `volume`, `snooze`, `fade` and `device` give the defaults of the alarms
+/// `pause` gives the day on which the alarms run again
+fn parse_setting(s: &str, settings: &mut Settings) -> Result<(), String> {
+    let parse_coordinate = |value: &str, max: f64| -> Result<f64, String> {
+        match value.trim().parse::<f64>() {
+            Ok(v) if v.abs() <= max => Ok(v),
+            _ => Err(format!("Invalid coordinate: {}", value))
+        }
+    };
+
+    match s.split_once('=') {
+        Some(("latitude", value)) => settings.latitude = Some(parse_coordinate(value, 90.0)?),
+        Some(("longitude", value)) => settings.longitude = Some(parse_coordinate(value, 180.0)?),
+        Some(("timezone", value)) => settings.timezone = Zone::parse(value)?,
+        Some(("pause", value)) => settings.paused_until = Some(parse_date(value)?),
+        Some(("audio", value)) => settings.defaults.audio = Some(value.to_string()),
+        Some(("volume", value)) => match value.parse::<u32>() {
+            Ok(volume) if volume <= 100 => settings.defaults.volume = Some(value.to_string()),
+            _ => return Err(format!("Invalid volume: {}", value))
+        },
+        Some((key @ ("snooze" | "fade" | "device"), _)) => {
+            parse_options(&[s])?;
+            settings.defaults.options.retain(|option| option_key(option) != key);
+            settings.defaults.options.push(s.to_string());
         },
-        _ => {
-            println!("Invalid configuration: {}", s);
-            None
+        _ => return Err(format!("Unknown setting: {}", s))
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_setting() {
+    let mut settings = Settings::default();
+    assert!(parse_setting("latitude=48.8566", &mut settings).is_ok());
+    assert!(parse_setting("longitude=-2.3522", &mut settings).is_ok());
+    assert!(parse_setting("timezone=America/New_York", &mut settings).is_ok());
+    assert_eq!(settings, Settings {
+        latitude: Some(48.8566),
+        longitude: Some(-2.3522),
+        timezone: Zone::Named(chrono_tz::America::New_York),
+        ..Settings::default()
+    });
+    assert!(parse_setting("latitude=91", &mut settings).is_err());
+    assert!(parse_setting("pause=2026-11-02", &mut settings).is_ok());
+    assert_eq!(settings.paused_until, NaiveDate::from_ymd_opt(2026, 11, 2));
+    assert!(parse_setting("pause=02/11/2026", &mut settings).is_err());
+    assert!(parse_setting("altitude=35", &mut settings).is_err());
+
+    assert!(parse_setting("audio=/a.ogg", &mut settings).is_ok());
+    assert!(parse_setting("volume=60", &mut settings).is_ok());
+    assert!(parse_setting("fade=10", &mut settings).is_ok());
+    assert!(parse_setting("fade=30", &mut settings).is_ok());
+    assert_eq!(settings.defaults, AlarmFields {
+        audio: Some("/a.ogg".to_string()),
+        volume: Some("60".to_string()),
+        options: vec!["fade=30".to_string()],
+        ..AlarmFields::default()
+    });
+    assert!(parse_setting("volume=loud", &mut settings).is_err());
+    assert!(parse_setting("snooze=later", &mut settings).is_err());
+}
+
+/// Splits a line of the CSV-like format to its `;` separated fields, trimmed
+/// Blank lines and comments have no field: a `#` starts a comment at the start of a field or after a whitespace
+/// Text between double quotes is kept as is, `;`, `#` and spaces included, with the `\"`, `\\`, `\n` and `\t` escapes
+fn split_line(line: &str) -> Result<Vec<String>, String> {
+    split_comment(line).map(|(fields, _)| fields)
+}
+
+/// Splits a line like `split_line`, also giving the position where its trailing comment starts
+fn split_comment(line: &str) -> Result<(Vec<String>, Option<usize>), String> {
+    // trims the whitespace that is not quoted
+    let trimmed = |field: &[(char, bool)]| -> String {
+        let blank = |&(c, quoted): &(char, bool)| !quoted && c.is_whitespace();
+        let start = field.iter().position(|c| !blank(c)).unwrap_or(field.len());
+        let end = field.iter().rposition(|c| !blank(c)).map_or(start, |last| last + 1);
+        field[start..end].iter().map(|&(c, _)| c).collect()
+    };
+
+    let mut fields: Vec<String> = vec![];
+    // characters of the current field, with whether they are quoted
+    let mut field: Vec<(char, bool)> = vec![];
+    let mut previous: Option<char> = None;
+    // an empty quoted text still makes a field
+    let mut quoted_field = false;
+    let mut comment: Option<usize> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => loop {
+                quoted_field = true;
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(escaped @ ('"' | '\\')) => field.push((escaped, true)),
+                        Some('n') => field.push(('\n', true)),
+                        Some('t') => field.push(('\t', true)),
+                        Some(other) => return Err(format!("Invalid escape: \\{}", other)),
+                        None => return Err("Unterminated quote".to_string())
+                    },
+                    Some(quoted) => field.push((quoted, true)),
+                    None => return Err("Unterminated quote".to_string())
+                }
+            },
+            '#' if previous.is_none_or(|p| p.is_whitespace() || p == ';') => {
+                comment = Some(line.len() - chars.as_str().len() - 1);
+                break;
+            },
+            ';' => {
+                fields.push(trimmed(&field));
+                field.clear();
+            },
+            _ => field.push((c, false))
         }
+        previous = Some(c);
+    }
+
+    if !fields.is_empty() || !trimmed(&field).is_empty() || quoted_field {
+        fields.push(trimmed(&field));
+    }
+    Ok((fields, comment))
+}
+
+/// Writes a field so that `split_line` reads it back, quoted when needed
+fn quote_field(field: &str) -> String {
+    let special = |c: char| c == ';' || c == '#' || c == '"' || c == '\\' || c == '\n' || c == '\t';
+    let padded = field.starts_with(char::is_whitespace) || field.ends_with(char::is_whitespace);
+    if !field.contains(special) && !padded {
+        return field.to_string();
+    }
+
+    // quotes only the value of options, so that the key stays readable
+    let (key, value) = match field.split_once('=') {
+        Some((key, value)) if !key.contains(special) && !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric()) => (format!("{}=", key), value),
+        _ => (String::new(), field)
     };
+    let escaped: String = value.chars().map(|c| match c {
+        '"' => "\\\"".to_string(),
+        '\\' => "\\\\".to_string(),
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        c => c.to_string()
+    }).collect();
+    format!("{}\"{}\"", key, escaped)
+}
+
+/// Writes fields as a line of the CSV-like format
+fn join_fields(fields: &[String]) -> String {
+    fields.iter().map(|field| quote_field(field)).collect::<Vec<_>>().join(";")
+}
 
-    maybe_config
+#[test]
+fn test_join_fields() {
+    let fields: Vec<String> = ["Mon-Fri", "06:30", "/music/a;b #1.ogg", "", "label=Wake \"up\"", "message= Hi\n"]
+        .iter().map(|s| s.to_string()).collect();
+    let line = join_fields(&fields);
+    assert_eq!(line, r#"Mon-Fri;06:30;"/music/a;b #1.ogg";;label="Wake \"up\"";message=" Hi\n""#);
+    assert_eq!(split_line(&line), Ok(fields));
+    assert_eq!(split_comment("MTWTF__;06:30 # wake ; up"), Ok((vec!["MTWTF__".to_string(), "06:30".to_string()], Some(14))));
+    assert_eq!(split_comment(r#"MTWTF__;"é#";07:00"#).map(|(_, comment)| comment), Ok(None));
+}
+
+#[test]
+fn test_split_line() {
+    let fields = |expected: &[&str]| Ok(expected.iter().map(|s| s.to_string()).collect::<Vec<String>>());
+
+    assert_eq!(split_line(""), fields(&[]));
+    assert_eq!(split_line("   "), fields(&[]));
+    assert_eq!(split_line("# You may add other configurations"), fields(&[]));
+    assert_eq!(split_line("  # indented comment"), fields(&[]));
+    assert_eq!(split_line(" MTWTF__ ; 06:30:00 ;/a.ogg; 75 "), fields(&["MTWTF__", "06:30:00", "/a.ogg", "75"]));
+    assert_eq!(split_line("MTWTF__;06:30:00;/a.ogg;75 # weekdays"), fields(&["MTWTF__", "06:30:00", "/a.ogg", "75"]));
+    assert_eq!(split_line("MTWTF__;06:30:00;/a.ogg;# no volume"), fields(&["MTWTF__", "06:30:00", "/a.ogg", ""]));
+    assert_eq!(split_line("MTWTF__;06:30:00;/music/#1.ogg"), fields(&["MTWTF__", "06:30:00", "/music/#1.ogg"]));
+    assert_eq!(split_line(r#"MTWTF__;06:30:00;"/music/a;b #2.ogg";75;label=" Wake \"up\" ""#),
+               fields(&["MTWTF__", "06:30:00", "/music/a;b #2.ogg", "75", "label= Wake \"up\" "]));
+    assert_eq!(split_line(r#"message="Line 1\nLine 2\\""#), fields(&["message=Line 1\nLine 2\\"]));
+    assert_eq!(split_line(r#""""#), fields(&[""]));
+    assert!(split_line(r#"label="Wake up"#).is_err());
+    assert!(split_line(r#"label="Wake\up""#).is_err());
+}
+
+/// Kind of a line of the CSV-like format, given its fields
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineKind {
+    /// Blank line or comment
+    Blank,
+    Setting,
+    Template,
+    Include,
+    Alarm
+}
+
+/// Text following a keyword that starts a field, such as the name in `template <name>`
+fn keyword_argument<'a>(field: &'a str, keyword: &str) -> Option<&'a str> {
+    match field.strip_prefix(keyword) {
+        Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => Some(rest.trim()),
+        _ => None
+    }
+}
+
+/// Finds the kind of a line from its fields
+fn line_kind(fields: &[String]) -> LineKind {
+    match fields {
+        [] => LineKind::Blank,
+        [single] if keyword_argument(single, "include").is_some() => LineKind::Include,
+        [first, ..] if keyword_argument(first, "template").is_some() => LineKind::Template,
+        [single] if single.contains('=') => LineKind::Setting,
+        _ => LineKind::Alarm
+    }
+}
+
+/// Reads the `;` separated fields of an alarm or template line, empty fields are missing
+fn split_fields(fields: &[String]) -> AlarmFields {
+    let positional = |index: usize| fields.get(index).filter(|field| !field.is_empty()).map(|field| field.to_string());
+    AlarmFields {
+        days: positional(0),
+        time: positional(1),
+        audio: positional(2),
+        volume: positional(3),
+        options: fields.iter().skip(4).map(|option| option.to_string()).collect()
+    }
+}
+
+/// Parses a `template <name>;days;time;audio;volume;options` line to a named set of fields
+fn parse_template(s: &str) -> Result<(String, AlarmFields), String> {
+    let split = split_line(s)?;
+    let name = split.first().and_then(|first| keyword_argument(first, "template")).unwrap_or("");
+    if name.is_empty() {
+        return Err("Missing template name".to_string());
+    }
+
+    let fields = split_fields(&split[1..]);
+    if fields.option("template").is_some() {
+        return Err("Templates can not inherit from another template".to_string());
+    }
+    Ok((name.to_string(), fields))
+}
+
+#[test]
+fn test_parse_template() {
+    assert_eq!(parse_template("template wake;MTWTF__;;/a.ogg;;fade=30"), Ok(("wake".to_string(), AlarmFields {
+        days: Some("MTWTF__".to_string()),
+        audio: Some("/a.ogg".to_string()),
+        options: vec!["fade=30".to_string()],
+        ..AlarmFields::default()
+    })));
+    assert_eq!(parse_template("template quiet").map(|(_, fields)| fields), Ok(AlarmFields::default()));
+    assert!(parse_template("template ;MTWTF__").is_err());
+    assert!(parse_template("template wake;;;;;template=other").is_err());
+}
+
+/// Builds an alarm config from its fields, completed by its template, then by the defaults
+fn build_alarm(fields: &AlarmFields, settings: &Settings) -> Result<AlarmConfig, String> {
+    let fields = match fields.option("template") {
+        Some(name) => fields.inherit(settings.templates.get(name).ok_or(format!("Unknown template: {}", name))?),
+        None => fields.clone()
+    };
+    let fields = fields.inherit(&settings.defaults);
+    let fields = match fields.option("every") {
+        Some(_) => fields.inherit(&reminder_defaults()),
+        None => fields
+    };
+
+    let the_days = fields.days.as_deref().unwrap_or("MTWTFSS");
+    let the_time = fields.time.as_deref().ok_or("Missing time")?;
+    let the_audio = match fields.audio.as_deref() {
+        Some(audio) => audio,
+        None if fields.option("every").is_some() => "",
+        None => return Err("Missing audio file".to_string())
+    };
+    let volume = fields.volume.as_deref().map_or(100, |volume| parse_int_with_min_max(volume, 0, 100));
+    let options: Vec<&str> = fields.options.iter()
+        .map(|option| option.as_str())
+        .filter(|option| option_key(option) != "template")
+        .collect();
+
+    let selected_days = parse_days(the_days)?;
+    let (time, time_schedule) = parse_time_field(the_time, settings)?;
+    let alarm_options = parse_options(&options)?;
+
+    let schedule = match (time_schedule, alarm_options.schedule, alarm_options.every) {
+        (Some(_), Schedule::Calendar(_), _) => return Err("Sun events and time windows can not be combined with a calendar".to_string()),
+        (Some(Schedule::Random(until)), _, Some(every)) => Schedule::Interval(IntervalRule { every, until }),
+        (_, _, Some(_)) => return Err("Option every requires a time window such as 09:00..18:00".to_string()),
+        (Some(schedule), _, None) | (None, schedule, None) => schedule
+    };
+
+    Ok(AlarmConfig {
+        days: selected_days,
+        time,
+        audio_file: the_audio.to_string(),
+        volume,
+        last_run: chrono::Local::now(),
+        schedule,
+        timezone: alarm_options.timezone.unwrap_or(settings.timezone),
+        label: alarm_options.label,
+        message: alarm_options.message,
+        snooze: alarm_options.snooze.unwrap_or(DEFAULT_SNOOZE),
+        fade: alarm_options.fade.unwrap_or(0),
+        device: alarm_options.device,
+        disabled: alarm_options.enabled == Some(false),
+        paused_until: settings.paused_until,
+        first_day: alarm_options.first_day,
+        last_day: alarm_options.last_day,
+        count: alarm_options.count,
+        runs: 0,
+        origin: Origin::default(),
+        exception: None,
+        resolved: vec![]
+    })
+}
+
+/// Fields of the reminders that neither they nor the defaults of the file set: half volume, without snooze
+/// Their audio is left empty, the chime is chosen when they run
+fn reminder_defaults() -> AlarmFields {
+    AlarmFields {
+        volume: Some(REMINDER_VOLUME.to_string()),
+        options: vec!["snooze=0".to_string()],
+        ..AlarmFields::default()
+    }
+}
+
+/// Parses a string line to an alarm config
+/// Empty days, audio and volume fields are inherited from the template and the defaults
+fn parse_configuration(s: &str, settings: &Settings) -> Result<AlarmConfig, String> {
+    let split = split_line(s)?;
+
+    match split.as_slice() {
+        [_, _, _, _, ..] => build_alarm(&split_fields(&split), settings),
+        _ => Err("expected days;time;audio;volume".to_string())
+    }
 }
 
 #[test]
 fn test_parse_configuration() {
     let config_str = "MTWTF__;06:30:15;/home/myhome/audio.ogg;75";
-    let config_opt = parse_configuration(config_str);
-    assert!(config_opt.is_some());
+    let config_opt = parse_configuration(config_str, &Settings::default());
+    assert!(config_opt.is_ok());
 
     let config = config_opt.unwrap();
     assert_eq!(config.days, vec![true,true,true,true,true,false,false]);
     assert_eq!(config.time, Time { hours: 6, minutes: 30, seconds: 15});
     assert_eq!(config.audio_file, "/home/myhome/audio.ogg");
     assert_eq!(config.volume, 75);
+    assert_eq!((config.snooze, config.fade, config.device), (DEFAULT_SNOOZE, 0, None));
 
     let fake_config_str = "MTWTF__;06:30:15;/home/myhome/audio.ogg;75;unknown;";
-    let fake_config_opt = parse_configuration(fake_config_str);
-    assert!(fake_config_opt.is_none());
+    let fake_config_opt = parse_configuration(fake_config_str, &Settings::default());
+    assert!(fake_config_opt.is_err());
+
+    let calendar_config_str = "MTWTF__;07:00:00;/home/myhome/audio.ogg;75;calendar=/home/myhome/oncall.ics;before=01:30:00";
+    let calendar_config = parse_configuration(calendar_config_str, &Settings::default()).unwrap();
+    assert_eq!(calendar_config.time, Time { hours: 7, minutes: 0, seconds: 0});
+    assert_eq!(calendar_config.schedule, Schedule::Calendar(CalendarRule {
+        path: "/home/myhome/oncall.ics".to_string(),
+        category: None,
+        before: 5400
+    }));
+
+    let settings = Settings { latitude: Some(48.85), longitude: Some(2.35), ..Settings::default() };
+    let solar_config = parse_configuration("MTWTFSS;sunrise-00:20;/home/myhome/audio.ogg;75", &settings).unwrap();
+    assert_eq!(solar_config.schedule, Schedule::Solar(SolarRule {
+        event: SolarEvent::Sunrise, offset: -1200, latitude: 48.85, longitude: 2.35
+    }));
+    assert!(parse_configuration("MTWTFSS;sunrise;/home/myhome/audio.ogg;75;calendar=/a.ics", &settings).is_err());
+    assert!(parse_configuration("MTWTFSS;07:00:00;;75", &settings).is_err());
+    assert!(parse_configuration("MTWTFSS;07:00:00;/a.ogg;75;template=unknown", &settings).is_err());
+    assert!(parse_configuration("MTWTFSS;07:00:00", &settings).is_err());
+
+    let reminder = parse_configuration("MTWTF__;09:00..18:00;;;every=45m;label=Stretch", &settings).unwrap();
+    assert_eq!(reminder.schedule, Schedule::Interval(IntervalRule { every: 2700, until: Time { hours: 18, minutes: 0, seconds: 0 } }));
+    assert_eq!((reminder.audio_file.as_str(), reminder.volume, reminder.snooze), ("", 50, 0));
+    assert!(reminder.is_reminder());
+    // the defaults of the file come first
+    let defaults = Settings { defaults: AlarmFields { audio: Some("/a.ogg".to_string()), ..AlarmFields::default() }, ..settings.clone() };
+    let reminder = parse_configuration("MTWTF__;09:00..18:00;;;every=45m;label=Stretch", &defaults).unwrap();
+    assert_eq!((reminder.audio_file.as_str(), reminder.volume), ("/a.ogg", 50));
+    assert_eq!(parse_configuration("MTWTF__;09:00..18:00;/b.ogg;20;every=1h", &defaults).unwrap().volume, 20);
+    assert!(parse_configuration("MTWTF__;09:00;;;every=45m", &defaults).is_err());
+    assert!(parse_configuration("MTWTF__;sunrise..18:00;;;every=45m", &defaults).is_err());
+    assert!(parse_configuration("MTWTF__;09:00..18:00;;;every=often", &defaults).is_err());
+    assert!(parse_configuration("MTWTF__;09:00..18:00;;;every=1193047h", &defaults).is_err());
+    assert!(parse_configuration("MTWTF__;09:00..18:00;;;every=0m", &defaults).is_err());
 }
 
-/// Retrieve the configuration from a configuration file
-pub fn retrieve_configuration(path: &str) -> Vec<AlarmConfig> {
-    let mut buff: Vec<AlarmConfig> = Vec::new();
+/// Place in the configuration files where an alarm is defined or a problem is found
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Origin {
+    pub file: PathBuf,
+    /// Line number starting at 1, when known
+    pub line: Option<usize>
+}
 
-    match OpenOptions::new().truncate(false).read(true).open(path) {
-        Ok(ref mut file) => {
-            use std::io::Read;
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.file.display(), line),
+            None => write!(f, "{}", self.file.display())
+        }
+    }
+}
+
+/// How serious a problem of the configuration is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// The alarm or setting is ignored
+    Error,
+    /// The configuration is used but probably not as intended
+    Warning
+}
+
+/// Problem found while reading the configuration files
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub origin: Origin,
+    pub severity: Severity,
+    pub message: String
+}
+
+impl Diagnostic {
 
-            let mut buff_str = String::new();
-            let read_result = file.read_to_string(&mut buff_str);
+    pub fn error(origin: Origin, message: String) -> Diagnostic {
+        Diagnostic { origin, severity: Severity::Error, message }
+    }
 
-            match read_result {
-                Ok(_) => {
+    pub fn warning(origin: Origin, message: String) -> Diagnostic {
+        Diagnostic { origin, severity: Severity::Warning, message }
+    }
+}
 
-                    let parsed = buff_str
-                        .lines()
-                        .flat_map(|line| parse_configuration(&line))
-                        .collect::<Vec<_>>();
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        };
+        write!(f, "{}: {}: {}", self.origin, severity, self.message)
+    }
+}
+
+/// Sets the place where an alarm is defined, and resolves its own paths relative to the directory of the file,
+/// the paths inherited from defaults and templates being already resolved where they are set
+fn place(mut alarm: AlarmConfig, file: &Path, line: Option<usize>) -> Result<AlarmConfig, String> {
+    let directory = paths::directory_of(file);
+    if !alarm.audio_file.is_empty() {
+        alarm.audio_file = paths::expand(&alarm.audio_file, &directory)?;
+    }
+    if let Schedule::Calendar(ref mut rule) = alarm.schedule {
+        rule.path = paths::expand(&rule.path, &directory)?;
+    }
+    alarm.origin = Origin { file: file.to_path_buf(), line };
+    Ok(alarm)
+}
+
+/// Reads configuration files and the files they include, collecting the problems found
+#[derive(Default)]
+struct Loader {
+    diagnostics: Vec<Diagnostic>,
+    /// Files being read, to detect include cycles
+    reading: Vec<PathBuf>
+}
+
+impl Loader {
+
+    /// Keeps a problem found at some place of a file
+    fn report(&mut self, file: &Path, line: Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic::error(Origin { file: file.to_path_buf(), line }, message));
+    }
+
+    /// Reads the alarms of a file, TOML when its extension is `.toml`, CSV-like otherwise
+    /// The file starts with the given settings, it returns them completed by its own settings
+    fn load(&mut self, path: &Path, settings: &Settings) -> (Vec<AlarmConfig>, Settings) {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.reading.contains(&canonical) {
+            self.report(path, None, "Include cycle, file already being read".to_string());
+            return (vec![], settings.clone());
+        }
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                self.report(path, None, format!("Unable to read file: {}", err));
+                return (vec![], settings.clone());
+            }
+        };
+
+        // a UTF-8 byte order mark may start files written on Windows
+        let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+
+        self.reading.push(canonical);
+        let loaded = if path.extension().is_some_and(|extension| extension == "toml") {
+            toml_file::parse(self, path, content, settings)
+        } else {
+            self.parse_lines(path, content, settings)
+        };
+        self.reading.pop();
+
+        loaded
+    }
+
+    /// Reads the alarms of an included file, given relative to the directory of the including file
+    fn include(&mut self, from: &Path, target: &str, settings: &Settings) -> Vec<AlarmConfig> {
+        match paths::expand(target, &paths::directory_of(from)) {
+            Ok(path) => self.load(Path::new(&path), settings).0,
+            Err(err) => {
+                self.report(from, None, format!("Invalid include: {}", err));
+                vec![]
+            }
+        }
+    }
 
-                    buff.extend(parsed.iter().cloned());
+    /// Parses the content of a CSV-like configuration file
+    /// Settings and templates apply to all the alarms of the file, wherever they are written,
+    /// and to the files it includes
+    fn parse_lines(&mut self, path: &Path, content: &str, base: &Settings) -> (Vec<AlarmConfig>, Settings) {
+        // fields of the lines, by line number
+        let lines: Vec<_> = content.lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line, split_line(line)))
+            .collect();
+
+        let directory = paths::directory_of(path);
+        let mut settings = base.clone();
+        for (number, line, split) in lines.iter() {
+            let fields = match *split {
+                Ok(ref fields) => fields,
+                Err(_) => continue
+            };
+            match line_kind(fields) {
+                LineKind::Setting => {
+                    let mut changed = settings.clone();
+                    match parse_setting(&fields[0], &mut changed).and_then(|_| changed.defaults.anchor(&directory)) {
+                        Ok(defaults) => settings = Settings { defaults, ..changed },
+                        Err(err) => self.report(path, Some(*number), format!("Invalid setting: {}", err))
+                    }
+                },
+                LineKind::Template => match parse_template(line).and_then(|(name, fields)| Ok((name, fields.anchor(&directory)?))) {
+                    Ok((name, fields)) => { settings.templates.insert(name, fields); },
+                    Err(err) => self.report(path, Some(*number), format!("Invalid template: {}", err))
                 },
-                Err(err) => { panic!("Unable to read file: {}", err); }
+                _ => {}
             }
-        },
-        Err(err) => { panic!("Failed to open configuration file: {}", err); }
+        }
+
+        let mut alarms: Vec<AlarmConfig> = vec![];
+        for (number, line, split) in lines.iter() {
+            let fields = match *split {
+                Ok(ref fields) => fields,
+                Err(ref err) => {
+                    self.report(path, Some(*number), format!("Invalid line: {}", err));
+                    continue;
+                }
+            };
+            match line_kind(fields) {
+                LineKind::Include => {
+                    let target = keyword_argument(&fields[0], "include").unwrap_or("");
+                    let included = self.include(path, target, &settings);
+                    alarms.extend(included);
+                },
+                LineKind::Alarm => match parse_configuration(line, &settings).and_then(|alarm| place(alarm, path, Some(*number))) {
+                    Ok(alarm) => alarms.push(alarm),
+                    Err(err) => self.report(path, Some(*number), format!("Invalid configuration: {}", err))
+                },
+                LineKind::Blank | LineKind::Setting | LineKind::Template => {}
+            }
+        }
+
+        (alarms, settings)
+    }
+}
+
+#[test]
+fn test_parse_lines() {
+    let mut loader = Loader::default();
+    let (alarms, _) = loader.parse_lines(Path::new("config"), "audio=/default.ogg
+volume=50
+snooze=5
+template weekday;MTWTF__;;/weekday.ogg;;fade=30
+_____SS;09:00:00;;
+;06:30:00;;80;template=weekday;snooze=10
+MTWTFSS;noon;;
+", &Settings::default());
+
+    assert_eq!(alarms.len(), 2);
+
+    assert_eq!(alarms[0].audio_file, "/default.ogg");
+    assert_eq!(alarms[0].volume, 50);
+    assert_eq!((alarms[0].snooze, alarms[0].fade), (5, 0));
+    assert_eq!(alarms[0].origin, Origin { file: PathBuf::from("config"), line: Some(5) });
+
+    assert_eq!(alarms[1].days, vec![true, true, true, true, true, false, false]);
+    assert_eq!(alarms[1].audio_file, "/weekday.ogg");
+    assert_eq!(alarms[1].volume, 80);
+    assert_eq!((alarms[1].snooze, alarms[1].fade), (10, 30));
+
+    assert_eq!(loader.diagnostics.len(), 1);
+    assert_eq!(loader.diagnostics[0].to_string(), "config:7: error: Invalid configuration: Invalid time: noon");
+}
+
+#[test]
+fn test_parse_lines_with_comments_and_quotes() {
+    let mut loader = Loader::default();
+    let (alarms, settings) = loader.parse_lines(Path::new("config"), "# Alarms of the house\r
+\r
+audio=\"/music/a;b.ogg\" # shared\r
+  MTWTF__ ; 06:30:00 ; ; 75 ; label=\"Wake; up\"   # weekdays\r
+_____SS;09:00:00;\"/music/#1.ogg\";60\r
+MTWTFSS;10:00:00;\"/unterminated.ogg;60\r
+", &Settings::default());
+
+    assert_eq!(settings.defaults.audio, Some("/music/a;b.ogg".to_string()));
+    assert_eq!(alarms.len(), 2);
+    assert_eq!(alarms[0].audio_file, "/music/a;b.ogg");
+    assert_eq!(alarms[0].label, Some("Wake; up".to_string()));
+    assert_eq!(alarms[0].volume, 75);
+    assert_eq!(alarms[1].audio_file, "/music/#1.ogg");
+    assert_eq!(loader.diagnostics.len(), 1);
+    assert_eq!(loader.diagnostics[0].to_string(), "config:6: error: Invalid line: Unterminated quote");
+}
+
+/// Reads the alarms of a configuration file, the files it includes
+/// and the `*.conf` files of the `conf.d` directory next to it, in name order
+/// The settings of the configuration file apply to the `conf.d` files
+pub fn load_configuration(path: &str) -> (Vec<AlarmConfig>, Vec<Diagnostic>) {
+    let path = Path::new(path);
+    let mut loader = Loader::default();
+    let (mut alarms, settings) = loader.load(path, &Settings::default());
+
+    let conf_d = path.parent().unwrap_or_else(|| Path::new("")).join("conf.d");
+    if let Ok(entries) = fs::read_dir(&conf_d) {
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|file| file.is_file() && file.extension().is_some_and(|extension| extension == "conf"))
+            .collect();
+        files.sort();
+
+        for file in files {
+            alarms.extend(loader.load(&file, &settings).0);
+        }
+    }
+
+    for (index, alarm) in alarms.iter().enumerate() {
+        let duplicate = alarms[..index].iter().any(|other| alarm.label.is_some() && other.label == alarm.label);
+        if duplicate {
+            loader.diagnostics.push(Diagnostic::warning(
+                alarm.origin.clone(),
+                format!("Duplicate label {}, commands will refer to the first alarm", alarm.label.as_ref().unwrap())
+            ));
+        }
     }
 
-    buff
+    (alarms, loader.diagnostics)
+}
+
+/// Latest day after `today` on which paused alarms run again, None when no alarm is paused
+pub fn paused_until(alarms: &[AlarmConfig], today: NaiveDate) -> Option<NaiveDate> {
+    alarms.iter().filter_map(|alarm| alarm.paused_until).filter(|until| *until > today).max()
+}
+
+/// FNV-1a hash of a text, which unlike the hasher of the standard library stays the same between versions
+fn digest(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[test]
+fn test_digest() {
+    assert_eq!(digest(""), 0xcbf29ce484222325);
+    assert_eq!(digest("a"), 0xaf63dc4c8601ec8c);
+}
+
+/// Checks if two alarms have the same definition, wherever they are defined and whatever their runs and exceptions
+pub fn same_definition(alarm: &AlarmConfig, other: &AlarmConfig) -> bool {
+    AlarmConfig {
+        last_run: other.last_run,
+        exception: other.exception.clone(),
+        runs: other.runs,
+        origin: other.origin.clone(),
+        resolved: other.resolved.clone(),
+        ..alarm.clone()
+    } == *other
+}
+
+/// Checks if two alarm lists are read from the same configuration, whatever the runs and exceptions of the alarms
+/// The alarms must also be defined at the same places, which the changes of the configuration files rely on
+pub fn same_configuration(alarms: &[AlarmConfig], others: &[AlarmConfig]) -> bool {
+    alarms.len() == others.len()
+        && alarms.iter().zip(others.iter()).all(|(alarm, other)| same_definition(alarm, other) && alarm.origin == other.origin)
+}
+
+/// Retrieve the configuration from a configuration file, printing the problems found
+/// Files with the `.toml` extension are read as TOML, other files with the CSV-like format
+pub fn retrieve_configuration(path: &str) -> Vec<AlarmConfig> {
+    let (alarms, diagnostics) = load_configuration(path);
+    for diagnostic in diagnostics.iter() {
+        println!("{}", diagnostic);
+    }
+    alarms
 }
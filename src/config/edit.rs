@@ -0,0 +1,434 @@
+use super::{join_fields, line_kind, load_configuration, option_key, split_comment, split_fields, split_line};
+use super::{AlarmConfig, AlarmFields, LineKind, Severity};
+use super::toml_file::OPTION_FIELDS;
+use chrono::NaiveDate;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use toml::Value;
+
+/// Lines of a configuration file, keeping its byte order mark and line endings
+#[derive(Debug, PartialEq)]
+struct Lines {
+    bom: bool,
+    crlf: bool,
+    lines: Vec<String>
+}
+
+impl Lines {
+
+    fn parse(content: &str) -> Lines {
+        let bom = content.starts_with('\u{feff}');
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        Lines {
+            bom,
+            crlf: content.contains("\r\n"),
+            lines: content.lines().map(|line| line.to_string()).collect()
+        }
+    }
+
+    /// Content of the file, each line ending with a line break
+    fn content(&self) -> String {
+        let newline = if self.crlf { "\r\n" } else { "\n" };
+        let mut content = if self.bom { "\u{feff}".to_string() } else { String::new() };
+        for line in self.lines.iter() {
+            content.push_str(line);
+            content.push_str(newline);
+        }
+        content
+    }
+
+    /// Index of a line given by its number starting at 1
+    fn index(&self, number: usize) -> Result<usize, String> {
+        match number {
+            0 => Err("Invalid line 0".to_string()),
+            _ if number > self.lines.len() => Err(format!("Line {} is past the end of the file", number)),
+            _ => Ok(number - 1)
+        }
+    }
+
+    /// Checks that a line of the CSV-like format defines an alarm and returns its fields and comment position
+    fn alarm_line(&self, number: usize) -> Result<(Vec<String>, Option<usize>), String> {
+        let (fields, comment) = split_comment(&self.lines[self.index(number)?])?;
+        match line_kind(&fields) {
+            LineKind::Alarm => Ok((fields, comment)),
+            _ => Err(format!("Line {} does not define an alarm, the file changed", number))
+        }
+    }
+
+    /// Lines of the `[[alarm]]` table starting at a line, up to its last field
+    fn alarm_table(&self, number: usize) -> Result<Range<usize>, String> {
+        let start = self.index(number)?;
+        if !self.lines[start].trim_start().starts_with("[[alarm]]") {
+            return Err(format!("Line {} does not start an [[alarm]] table, the file changed", number));
+        }
+
+        let next_table = self.lines.iter()
+            .skip(start + 1)
+            .position(|line| line.trim_start().starts_with('['))
+            .map_or(self.lines.len(), |position| start + 1 + position);
+        // comments before the next table belong to it
+        let end = (start + 1..next_table).rev()
+            .find(|&index| !is_blank_or_comment(&self.lines[index]))
+            .map_or(start + 1, |index| index + 1);
+        Ok(start..end)
+    }
+}
+
+/// Checks if a line of a TOML file holds no value
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+/// Checks if a configuration file is read as TOML
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "toml")
+}
+
+/// Value of an alarm field written in TOML
+fn toml_value(key: &str, value: &str) -> String {
+    match key {
+        "volume" | "snooze" | "fade" | "count" if value.parse::<i64>().is_ok() => value.to_string(),
+        "enabled" if value == "true" || value == "false" => value.to_string(),
+        "pause" | "from" | "to" if NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok() => value.to_string(),
+        _ => Value::String(value.to_string()).to_string()
+    }
+}
+
+/// Fields written at the start of the lines of the CSV-like format
+const POSITIONAL_FIELDS: [&str; 4] = ["days", "time", "audio", "volume"];
+
+/// Reads changes of alarm fields written `key=value;key=value`, an empty value removes the field
+fn parse_changes(changes: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    let fields = split_line(changes)?;
+    if fields.is_empty() {
+        return Err("expected key=value fields".to_string());
+    }
+    fields.iter().map(|field| match field.split_once('=') {
+        Some((key, _)) if !POSITIONAL_FIELDS.contains(&key) && !OPTION_FIELDS.contains(&key) => Err(format!("Unknown field: {}", key)),
+        Some((key, "")) => Ok((key.to_string(), None)),
+        Some((key, value)) => Ok((key.to_string(), Some(value.to_string()))),
+        None => Err(format!("expected key=value: {}", field))
+    }).collect()
+}
+
+#[test]
+fn test_parse_changes() {
+    assert_eq!(parse_changes("time=6:45; label=\"Wake; up\";message="), Ok(vec![
+        ("time".to_string(), Some("6:45".to_string())),
+        ("label".to_string(), Some("Wake; up".to_string())),
+        ("message".to_string(), None)
+    ]));
+    assert!(parse_changes("").is_err());
+    assert!(parse_changes("colour=blue").is_err());
+    assert!(parse_changes("6:45").is_err());
+}
+
+/// Key of a `key = value` line of a TOML table
+fn toml_key(line: &str) -> Option<&str> {
+    line.split_once('=').map(|(key, _)| key.trim())
+}
+
+/// Appends an alarm, the line as given for the CSV-like format or its fields as an `[[alarm]]` table for TOML
+/// Returns the line number where the alarm starts
+fn append(lines: &mut Lines, line: &str, fields: &AlarmFields, toml: bool) -> usize {
+    if !toml {
+        lines.lines.push(line.trim().to_string());
+        return lines.lines.len();
+    }
+
+    if lines.lines.last().is_some_and(|last| !last.trim().is_empty()) {
+        lines.lines.push(String::new());
+    }
+    lines.lines.push("[[alarm]]".to_string());
+    let number = lines.lines.len();
+
+    let positional = [("days", &fields.days), ("time", &fields.time), ("audio", &fields.audio), ("volume", &fields.volume)];
+    for (key, value) in positional.iter() {
+        if let Some(value) = value {
+            lines.lines.push(format!("{} = {}", key, toml_value(key, value)));
+        }
+    }
+    for (key, value) in fields.options.iter().filter_map(|option| option.split_once('=')) {
+        lines.lines.push(format!("{} = {}", key, toml_value(key, value)));
+    }
+    number
+}
+
+/// Removes the alarm starting at a line, and for TOML the blank lines following its table
+fn remove(lines: &mut Lines, number: usize, toml: bool) -> Result<(), String> {
+    if !toml {
+        lines.alarm_line(number)?;
+        lines.lines.remove(number - 1);
+        return Ok(());
+    }
+
+    let table = lines.alarm_table(number)?;
+    let end = (table.end..lines.lines.len())
+        .find(|&index| !lines.lines[index].trim().is_empty())
+        .unwrap_or(lines.lines.len());
+    lines.lines.drain(table.start..end);
+    // nor blank lines at the end of the file
+    while table.start >= lines.lines.len() && lines.lines.last().is_some_and(|last| last.trim().is_empty()) {
+        lines.lines.pop();
+    }
+    Ok(())
+}
+
+/// Sets a field of the alarm starting at a line, or removes it when there is no value
+/// Other fields and trailing comments are kept
+fn set_field(lines: &mut Lines, number: usize, key: &str, value: Option<&str>, toml: bool) -> Result<(), String> {
+    if !toml {
+        let (mut fields, comment) = lines.alarm_line(number)?;
+        let options: Vec<String> = fields.split_off(4.min(fields.len()));
+        fields.resize(4, String::new());
+        match POSITIONAL_FIELDS.iter().position(|positional| *positional == key) {
+            Some(index) => {
+                fields[index] = value.unwrap_or("").to_string();
+                fields.extend(options);
+            },
+            None => {
+                fields.extend(options.into_iter().filter(|option| option_key(option) != key));
+                fields.extend(value.map(|value| format!("{}={}", key, value)));
+            }
+        }
+
+        let line = &mut lines.lines[number - 1];
+        *line = match comment {
+            Some(position) => format!("{} {}", join_fields(&fields), &line[position..]),
+            None => join_fields(&fields)
+        };
+        return Ok(());
+    }
+
+    let table = lines.alarm_table(number)?;
+    let existing = table.clone().skip(1).find(|&index| toml_key(&lines.lines[index]) == Some(key));
+    match (existing, value) {
+        (Some(index), Some(value)) => lines.lines[index] = format!("{} = {}", key, toml_value(key, value)),
+        (Some(index), None) => { lines.lines.remove(index); },
+        (None, Some(value)) => lines.lines.insert(table.end, format!("{} = {}", key, toml_value(key, value))),
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+/// Sets a setting shared by all the alarms, or removes it when there is no value
+/// A new setting is written after the settings that come before the first alarm, template or table
+fn set_setting(lines: &mut Lines, key: &str, value: Option<&str>, toml: bool) {
+    // kind of each line: a setting with its key, a blank line or comment, or the start of the alarms
+    let setting_key = |line: &str| -> Result<Option<String>, ()> {
+        if toml && is_blank_or_comment(line) {
+            return Ok(None);
+        }
+        if toml {
+            return if line.trim_start().starts_with('[') { Err(()) } else { Ok(toml_key(line).map(|key| key.to_string())) };
+        }
+        match split_line(line) {
+            Ok(ref fields) if line_kind(fields) == LineKind::Setting => Ok(Some(option_key(&fields[0]).to_string())),
+            Ok(ref fields) if fields.is_empty() => Ok(None),
+            _ => Err(())
+        }
+    };
+    let settings: Vec<(usize, String)> = lines.lines.iter()
+        .map(|line| setting_key(line))
+        .take_while(|kind| kind.is_ok())
+        .enumerate()
+        .filter_map(|(index, kind)| kind.ok().flatten().map(|key| (index, key)))
+        .collect();
+
+    let line = value.map(|value| if toml { format!("{} = {}", key, toml_value(key, value)) } else { format!("{}={}", key, value) });
+    match (settings.iter().find(|(_, setting)| setting == key), line) {
+        (Some(&(index, _)), Some(line)) => lines.lines[index] = line,
+        (Some(&(index, _)), None) => { lines.lines.remove(index); },
+        (None, Some(line)) => lines.lines.insert(settings.last().map_or(0, |&(index, _)| index + 1), line),
+        (None, None) => {}
+    }
+}
+
+/// Reads the lines of a configuration file, a missing file has no line
+fn read(path: &Path) -> Result<Lines, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Lines::parse(&content)),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Lines::parse("")),
+        Err(err) => Err(format!("Unable to read {}: {}", path.display(), err))
+    }
+}
+
+fn write(path: &Path, lines: &Lines) -> Result<(), String> {
+    fs::write(path, lines.content()).map_err(|err| format!("Unable to write {}: {}", path.display(), err))
+}
+
+/// Writes the changed lines of a file, then reads the configuration again
+/// The file is restored when there is an error at the given line, where the changed alarm starts
+fn apply(config: &Path, file: &Path, original: &Lines, changed: &Lines, line: Option<usize>) -> Result<Vec<AlarmConfig>, String> {
+    write(file, changed)?;
+    let (alarms, diagnostics) = load_configuration(&config.to_string_lossy());
+
+    let error = diagnostics.iter().find(|diagnostic| {
+        diagnostic.severity == Severity::Error && diagnostic.origin.file == file && line.is_some() && diagnostic.origin.line == line
+    });
+    match error {
+        Some(error) => {
+            write(file, original)?;
+            Err(error.message.clone())
+        },
+        None => Ok(alarms)
+    }
+}
+
+/// Changes the lines of the file where an alarm is defined, then reads the configuration again
+fn change<F>(config: &Path, alarm: &AlarmConfig, validate: bool, change: F) -> Result<Vec<AlarmConfig>, String>
+    where F: FnOnce(&mut Lines, usize, bool) -> Result<(), String> {
+    let path = &alarm.origin.file;
+    let number = alarm.origin.line.ok_or(format!("The place of the alarm in {} is unknown", path.display()))?;
+    let original = read(path)?;
+    let mut lines = read(path)?;
+    change(&mut lines, number, is_toml(path))?;
+    apply(config, path, &original, &lines, if validate { Some(number) } else { None })
+}
+
+/// Adds an alarm, written as a `days;time;audio;volume[;key=value...]` line, at the end of a configuration file
+/// TOML files get an `[[alarm]]` table with the same fields
+/// Returns the alarms read again with the position of the added alarm, the file is restored when the alarm is invalid
+pub fn add_alarm(config: &Path, line: &str) -> Result<(Vec<AlarmConfig>, usize), String> {
+    let split = split_line(line)?;
+    if line_kind(&split) != LineKind::Alarm || split.len() < 4 {
+        return Err("expected days;time;audio;volume".to_string());
+    }
+
+    let original = read(config)?;
+    let mut lines = read(config)?;
+    let number = append(&mut lines, line, &split_fields(&split), is_toml(config));
+    let alarms = apply(config, config, &original, &lines, Some(number))?;
+
+    let position = alarms.iter()
+        .position(|alarm| alarm.origin.file == config && alarm.origin.line == Some(number))
+        .ok_or("The added alarm is not read back")?;
+    Ok((alarms, position))
+}
+
+/// Changes fields of an alarm, written `key=value;key=value`, in the file where it is defined
+/// An empty value removes the field, so that it is inherited from the template or the defaults
+/// Returns the alarms read again, the file is restored when the alarm becomes invalid
+pub fn edit_alarm(config: &Path, alarm: &AlarmConfig, changes: &str) -> Result<Vec<AlarmConfig>, String> {
+    let changes = parse_changes(changes)?;
+    change(config, alarm, true, |lines, number, toml| {
+        for (key, value) in changes.iter() {
+            set_field(lines, number, key, value.as_deref(), toml)?;
+        }
+        Ok(())
+    })
+}
+
+/// Removes an alarm from the file where it is defined, returns the alarms read again
+pub fn remove_alarm(config: &Path, alarm: &AlarmConfig) -> Result<Vec<AlarmConfig>, String> {
+    change(config, alarm, false, remove)
+}
+
+/// Enables or disables an alarm in the file where it is defined, with its `enabled` field
+/// Returns the alarms read again
+pub fn set_enabled(config: &Path, alarm: &AlarmConfig, enabled: bool) -> Result<Vec<AlarmConfig>, String> {
+    let value = if enabled { None } else { Some("false") };
+    change(config, alarm, true, |lines, number, toml| set_field(lines, number, "enabled", value, toml))
+}
+
+/// Pauses the alarms until a day with the `pause` setting of the configuration file, or removes the setting
+/// Returns the alarms read again
+pub fn set_pause(config: &Path, until: Option<NaiveDate>) -> Result<Vec<AlarmConfig>, String> {
+    let original = read(config)?;
+    let mut lines = read(config)?;
+    let until = until.map(|until| until.format("%Y-%m-%d").to_string());
+    set_setting(&mut lines, "pause", until.as_deref(), is_toml(config));
+    apply(config, config, &original, &lines, None)
+}
+
+#[test]
+fn test_lines() {
+    let content = "\u{feff}# alarms\r\nMTWTF__;06:30;/a.ogg;75\r\n";
+    let lines = Lines::parse(content);
+    assert_eq!(lines.lines, vec!["# alarms", "MTWTF__;06:30;/a.ogg;75"]);
+    assert_eq!(lines.content(), content);
+    assert_eq!(Lines::parse("a\nb").content(), "a\nb\n");
+    assert!(lines.index(3).is_err());
+    assert!(lines.alarm_line(1).is_err());
+}
+
+#[test]
+fn test_edit_lines() {
+    let mut lines = Lines::parse("volume=60
+MTWTF__ ; 06:30 ; \"/music/a;b.ogg\" ; 75 ; label=Wake   # weekdays
+_____SS;09:00;/b.ogg;60;enabled=false
+");
+    set_field(&mut lines, 2, "enabled", Some("false"), false).unwrap();
+    set_field(&mut lines, 3, "enabled", None, false).unwrap();
+    assert_eq!(lines.lines[1], "MTWTF__;06:30;\"/music/a;b.ogg\";75;label=Wake;enabled=false # weekdays");
+    assert_eq!(lines.lines[2], "_____SS;09:00;/b.ogg;60");
+    assert!(set_field(&mut lines, 1, "enabled", None, false).is_err());
+
+    set_field(&mut lines, 3, "time", Some("9h"), false).unwrap();
+    set_field(&mut lines, 3, "volume", None, false).unwrap();
+    set_field(&mut lines, 3, "label", Some("Lazy"), false).unwrap();
+    assert_eq!(lines.lines[2], "_____SS;9h;/b.ogg;;label=Lazy");
+
+    let fields = split_fields(&split_line("daily;7h;/c.ogg;50").unwrap());
+    assert_eq!(append(&mut lines, " daily;7h;/c.ogg;50 ", &fields, false), 4);
+    remove(&mut lines, 2, false).unwrap();
+    assert_eq!(lines.content(), "volume=60\n_____SS;9h;/b.ogg;;label=Lazy\ndaily;7h;/c.ogg;50\n");
+}
+
+#[test]
+fn test_edit_tables() {
+    let mut lines = Lines::parse(r#"volume = 60
+
+[[alarm]]
+time = 06:30:00 # before work
+audio = "/a.ogg"
+
+# weekends
+[[alarm]]
+days = "_____SS"
+time = "09:00"
+audio = "/b.ogg"
+enabled = false
+"#);
+    set_field(&mut lines, 3, "enabled", Some("false"), true).unwrap();
+    // the second table moved down by the line added to the first one
+    set_field(&mut lines, 9, "enabled", None, true).unwrap();
+    assert!(set_field(&mut lines, 4, "enabled", None, true).is_err());
+    assert_eq!(lines.lines[3..6], ["time = 06:30:00 # before work", "audio = \"/a.ogg\"", "enabled = false"]);
+    assert_eq!(lines.lines.len(), 12);
+
+    let fields = split_fields(&split_line(r#"daily;7h;"/c \"d\".ogg";50;label=Tea"#).unwrap());
+    assert_eq!(append(&mut lines, "", &fields, true), 14);
+    assert_eq!(lines.lines[13..], ["[[alarm]]", "days = \"daily\"", "time = \"7h\"", "audio = '/c \"d\".ogg'", "volume = 50", "label = \"Tea\""]);
+
+    remove(&mut lines, 3, true).unwrap();
+    remove(&mut lines, 9, true).unwrap();
+    assert_eq!(lines.content(), "volume = 60
+
+# weekends
+[[alarm]]
+days = \"_____SS\"
+time = \"09:00\"
+audio = \"/b.ogg\"
+");
+}
+
+#[test]
+fn test_edit_settings() {
+    let mut lines = Lines::parse("# alarms\nvolume=60\n\n# weekdays\nMTWTF__;06:30;/a.ogg;75\n");
+    set_setting(&mut lines, "pause", Some("2026-11-02"), false);
+    assert_eq!(lines.lines[..3], ["# alarms", "volume=60", "pause=2026-11-02"]);
+    set_setting(&mut lines, "pause", Some("2026-11-09"), false);
+    assert_eq!(lines.lines[2], "pause=2026-11-09");
+    set_setting(&mut lines, "pause", None, false);
+    assert_eq!(lines.content(), "# alarms\nvolume=60\n\n# weekdays\nMTWTF__;06:30;/a.ogg;75\n");
+
+    let mut lines = Lines::parse("# alarms\n[[alarm]]\ntime = 06:30:00\n");
+    set_setting(&mut lines, "pause", Some("2026-11-02"), true);
+    assert_eq!(lines.lines, ["pause = 2026-11-02", "# alarms", "[[alarm]]", "time = 06:30:00"]);
+    set_setting(&mut lines, "time", None, true);
+    assert_eq!(lines.lines.len(), 4);
+}
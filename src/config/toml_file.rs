@@ -0,0 +1,412 @@
+use super::{build_alarm, parse_setting, place, AlarmConfig, AlarmFields, Loader, Settings};
+use paths;
+use std::ops::Range;
+use std::path::Path;
+use toml::{Table, Value};
+use toml_edit::{ImDocument, Item};
+
+/// Fields of an `[[alarm]]` table, other than the days, time, audio and volume,
+/// mapped to the `key=value` fields of the CSV-like format
+pub(super) const OPTION_FIELDS: [&str; 15] = [
+    "calendar", "category", "before", "timezone", "label", "message", "snooze", "fade", "device", "enabled", "from", "to", "count", "every", "template"
+];
+
+/// Fields of the `[defaults]` table
+const DEFAULT_FIELDS: [&str; 5] = ["audio", "volume", "snooze", "fade", "device"];
+
+/// Reads an optional string field of a table
+fn string_field<'a>(table: &'a Table, key: &str) -> Result<Option<&'a str>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(other) => Err(format!("Field {} must be a string, not a {}", key, other.type_str()))
+    }
+}
+
+/// Reads an optional option field, either a string, an integer, a boolean or a TOML local date such as `2026-09-01`
+fn option_field(table: &Table, key: &str) -> Result<Option<String>, String> {
+    match table.get(key) {
+        Some(Value::Integer(i)) => Ok(Some(i.to_string())),
+        Some(Value::Boolean(b)) => Ok(Some(b.to_string())),
+        Some(Value::Datetime(dt)) if dt.time.is_none() => Ok(Some(dt.to_string())),
+        _ => string_field(table, key).map(|value| value.map(|s| s.to_string()))
+    }
+}
+
+/// Reads the time field, either a string or a TOML local time such as `06:30:00`
+fn time_field(table: &Table) -> Result<Option<String>, String> {
+    match table.get("time") {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(Value::Datetime(dt)) if dt.date.is_none() && dt.time.is_some() => Ok(Some(dt.to_string())),
+        Some(other) => Err(format!("Field time must be a time or a string, not a {}", other.type_str()))
+    }
+}
+
+/// Reads the fields of an alarm, template or defaults table, among the allowed ones
+fn parse_fields(table: &Table, allowed: &[&str]) -> Result<AlarmFields, String> {
+    if let Some(unknown) = table.keys().find(|key| !allowed.contains(&key.as_str())) {
+        return Err(format!("Unknown field: {}", unknown));
+    }
+
+    let volume = match table.get("volume") {
+        None => None,
+        Some(&Value::Integer(v)) if (0..=100).contains(&v) => Some(v.to_string()),
+        Some(_) => return Err("Field volume must be an integer between 0 and 100".to_string())
+    };
+
+    let mut options: Vec<String> = vec![];
+    for key in OPTION_FIELDS.iter() {
+        if let Some(value) = option_field(table, key)? {
+            options.push(format!("{}={}", key, value));
+        }
+    }
+
+    Ok(AlarmFields {
+        days: string_field(table, "days")?.map(|s| s.to_string()),
+        time: time_field(table)?,
+        audio: string_field(table, "audio")?.map(|s| s.to_string()),
+        volume,
+        options
+    })
+}
+
+/// Fields allowed in `[[alarm]]` tables, templates allow the same fields but `template`
+fn alarm_fields() -> Vec<&'static str> {
+    ["days", "time", "audio", "volume"].iter().chain(OPTION_FIELDS.iter()).cloned().collect()
+}
+
+/// Parses the top level keys of the file, the `[defaults]` and `[templates.<name>]` tables, shared by all the alarms
+/// Invalid settings, defaults and templates are skipped and returned with the keys leading to them and their problem
+/// Relative paths of the defaults and templates are relative to the `directory` of the file
+fn parse_settings(table: &Table, base: &Settings, directory: &Path) -> (Settings, Vec<(Vec<String>, String)>) {
+    let mut settings = base.clone();
+    let mut problems: Vec<(Vec<String>, String)> = vec![];
+
+    for (key, value) in table.iter().filter(|&(key, _)| !["alarm", "defaults", "templates", "include"].contains(&key.as_str())) {
+        let value_str = match *value {
+            Value::String(ref s) => Ok(s.clone()),
+            Value::Float(f) => Ok(f.to_string()),
+            Value::Integer(i) => Ok(i.to_string()),
+            Value::Datetime(ref dt) => Ok(dt.to_string()),
+            ref other => Err(format!("Setting {} can not be a {}", key, other.type_str()))
+        };
+        if let Err(err) = value_str.and_then(|value_str| parse_setting(&format!("{}={}", key, value_str), &mut settings)) {
+            problems.push((vec![key.clone()], format!("Invalid setting: {}", err)));
+        }
+    }
+
+    match table.get("defaults") {
+        None => {},
+        Some(Value::Table(defaults)) => {
+            // validated as settings of the CSV-like format, kept only when all of them are valid
+            let mut with_defaults = settings.clone();
+            let parsed = parse_fields(defaults, &DEFAULT_FIELDS).and_then(|fields| {
+                for setting in fields.audio.iter().map(|audio| format!("audio={}", audio))
+                    .chain(fields.volume.iter().map(|volume| format!("volume={}", volume)))
+                    .chain(fields.options.iter().cloned()) {
+                    parse_setting(&setting, &mut with_defaults)?;
+                }
+                with_defaults.defaults.anchor(directory)
+            });
+            match parsed {
+                Ok(defaults) => settings = Settings { defaults, ..with_defaults },
+                Err(err) => problems.push((vec!["defaults".to_string()], format!("Invalid defaults: {}", err)))
+            }
+        },
+        Some(_) => problems.push((vec!["defaults".to_string()], "Invalid defaults: not a table".to_string()))
+    }
+
+    match table.get("templates") {
+        None => {},
+        Some(Value::Table(templates)) => {
+            let allowed: Vec<&str> = alarm_fields().into_iter().filter(|key| *key != "template").collect();
+            for (name, template) in templates.iter() {
+                let parsed = match *template {
+                    Value::Table(ref template) => parse_fields(template, &allowed).and_then(|fields| fields.anchor(directory)),
+                    _ => Err("not a table".to_string())
+                };
+                match parsed {
+                    Ok(fields) => { settings.templates.insert(name.clone(), fields); },
+                    Err(err) => problems.push((vec!["templates".to_string(), name.clone()], format!("Invalid template {}: {}", name, err)))
+                }
+            }
+        },
+        Some(_) => problems.push((vec!["templates".to_string()], "Invalid templates: not a table".to_string()))
+    }
+
+    (settings, problems)
+}
+
+/// Line of a position given by the TOML parser, starting at 1
+fn line_of(content: &str, span: Option<Range<usize>>) -> Option<usize> {
+    span.and_then(|span| content.get(..span.start)).map(|before| before.matches('\n').count() + 1)
+}
+
+/// Lines of the alarm tables, written as `[[alarm]]` tables or as an array of inline tables
+fn alarm_lines(content: &str, document: &ImDocument<&str>) -> Vec<Option<usize>> {
+    match document.get("alarm") {
+        Some(Item::ArrayOfTables(tables)) => tables.iter().map(|table| line_of(content, table.span())).collect(),
+        Some(Item::Value(toml_edit::Value::Array(values))) => values.iter().map(|value| line_of(content, value.span())).collect(),
+        _ => vec![]
+    }
+}
+
+/// Parses an `[[alarm]]` table to an alarm config
+fn parse_alarm(table: &Table, settings: &Settings) -> Result<AlarmConfig, String> {
+    build_alarm(&parse_fields(table, &alarm_fields())?, settings)
+}
+
+/// Reads the list of included files
+fn include_field(table: &Table) -> Result<Vec<String>, String> {
+    match table.get("include") {
+        None => Ok(vec![]),
+        Some(Value::Array(files)) => files.iter()
+            .map(|file| file.as_str().map(|s| s.to_string()).ok_or("Field include must list file names".to_string()))
+            .collect(),
+        Some(_) => Err("Field include must be an array".to_string())
+    }
+}
+
+/// Parses the content of a TOML configuration file, starting from the given settings
+/// Invalid alarms are reported and skipped, the alarms of included files follow the alarms of the file
+pub(super) fn parse(loader: &mut Loader, path: &Path, content: &str, base: &Settings) -> (Vec<AlarmConfig>, Settings) {
+    let table = match content.parse::<Table>() {
+        Ok(table) => table,
+        Err(err) => {
+            loader.report(path, None, format!("Invalid TOML configuration: {}", err));
+            return (vec![], base.clone());
+        }
+    };
+    // the same content read again with the positions of its items, to locate the problems
+    let document = ImDocument::parse(content).ok();
+
+    let (settings, problems) = parse_settings(&table, base, &paths::directory_of(path));
+    for (keys, err) in problems {
+        let item = document.as_ref().map(|document| document.as_item())
+            .and_then(|item| keys.iter().try_fold(item, |item, key| item.get(key)));
+        loader.report(path, item.and_then(|item| line_of(content, item.span())), err);
+    }
+
+    let alarm_lines = document.as_ref().map(|document| alarm_lines(content, document)).unwrap_or_default();
+
+    let mut alarms: Vec<AlarmConfig> = vec![];
+    match table.get("alarm") {
+        None => {},
+        Some(Value::Array(tables)) => {
+            for (index, alarm) in tables.iter().enumerate() {
+                let line = alarm_lines.get(index).cloned().flatten();
+                let parsed = match *alarm {
+                    Value::Table(ref table) => parse_alarm(table, &settings),
+                    _ => Err("not a table".to_string())
+                };
+                match parsed.and_then(|alarm| place(alarm, path, line)) {
+                    Ok(alarm) => alarms.push(alarm),
+                    Err(err) => loader.report(path, line, format!("Invalid alarm #{}: {}", index + 1, err))
+                }
+            }
+        },
+        Some(_) => loader.report(path, None, "Invalid configuration: alarms must be written as [[alarm]] tables".to_string())
+    };
+
+    match include_field(&table) {
+        Ok(files) => {
+            for file in files {
+                let included = loader.include(path, &file, &settings);
+                alarms.extend(included);
+            }
+        },
+        Err(err) => loader.report(path, None, err)
+    }
+
+    (alarms, settings)
+}
+
+/// Parses a TOML configuration without includes
+#[cfg(test)]
+fn parse_content(content: &str) -> Vec<AlarmConfig> {
+    parse(&mut Loader::default(), Path::new("config.toml"), content, &Settings::default()).0
+}
+
+#[test]
+fn test_parse() {
+    use super::{CalendarRule, Schedule, Time};
+    use zone::Zone;
+
+    let alarms = parse_content(r#"
+timezone = "Europe/Paris"
+pause = 2026-11-02
+
+[[alarm]]
+days = "MTWTF__"
+time = 06:30:00
+audio = "/home/me/audio.ogg"
+volume = 75
+
+[[alarm]]
+label = "On-call"
+message = "Check the pager"
+time = "07:00:00"
+audio = "/home/me/oncall.ogg"
+calendar = "/home/me/oncall.ics"
+before = "01:30:00"
+timezone = "Asia/Tokyo"
+
+[[alarm]]
+time = "07:00:00"
+audio = "/home/me/audio.ogg"
+volume = 150
+
+[[alarm]]
+time = "07:00:00"
+audio = "/home/me/audio.ogg"
+colour = "blue"
+"#);
+
+    assert_eq!(alarms.len(), 2);
+
+    assert_eq!(alarms[0].days, vec![true, true, true, true, true, false, false]);
+    assert_eq!(alarms[0].time, Time { hours: 6, minutes: 30, seconds: 0 });
+    assert_eq!(alarms[0].audio_file, "/home/me/audio.ogg");
+    assert_eq!(alarms[0].volume, 75);
+    assert_eq!(alarms[0].timezone, Zone::Named(::chrono_tz::Europe::Paris));
+    assert_eq!(alarms[0].paused_until, ::chrono::NaiveDate::from_ymd_opt(2026, 11, 2));
+
+    // defaults
+    assert_eq!(alarms[1].days, vec![true; 7]);
+    assert_eq!(alarms[1].volume, 100);
+    assert_eq!(alarms[1].timezone, Zone::Named(::chrono_tz::Asia::Tokyo));
+    assert_eq!(alarms[1].label, Some("On-call".to_string()));
+    assert_eq!(alarms[1].message, Some("Check the pager".to_string()));
+    assert_eq!(alarms[1].schedule, Schedule::Calendar(CalendarRule {
+        path: "/home/me/oncall.ics".to_string(),
+        category: None,
+        before: 5400
+    }));
+}
+
+#[test]
+fn test_parse_locates_problems() {
+    let mut loader = Loader::default();
+    let (alarms, _) = parse(&mut loader, Path::new("config.toml"), r#"
+[templates.loud]
+volume = 150
+
+  [[ alarm ]]
+  time = "07:00:00"
+  audio = "/a.ogg"
+
+[[alarm]] # the second alarm
+time = "noon"
+audio = "/a.ogg"
+"#, &Settings::default());
+
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(alarms[0].origin.line, Some(5));
+    let messages: Vec<String> = loader.diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect();
+    assert_eq!(messages, vec![
+        "config.toml:2: error: Invalid template loud: Field volume must be an integer between 0 and 100".to_string(),
+        "config.toml:9: error: Invalid alarm #2: Invalid time: noon".to_string()
+    ]);
+
+    let inline = "alarm = [\n  { time = \"07:00\" },\n  { time = \"08:00\" }\n]";
+    assert_eq!(alarm_lines(inline, &ImDocument::parse(inline).unwrap()), vec![Some(2), Some(3)]);
+}
+
+#[test]
+fn test_parse_invalid() {
+    assert!(parse_content("not = [toml").is_empty());
+    assert!(parse_content("alarm = 3").is_empty());
+    assert!(parse_content("[[alarm]]\naudio = \"/a.ogg\"").is_empty());
+    assert!(parse_content("[[alarm]]\ntime = 7\naudio = \"/a.ogg\"").is_empty());
+}
+
+#[test]
+fn test_parse_keeps_the_valid_settings() {
+    use zone::Zone;
+
+    let mut loader = Loader::default();
+    let (alarms, settings) = parse(&mut loader, Path::new("config.toml"), r#"
+timezone = "Asia/Tokyo"
+altitude = 35
+
+[defaults]
+volume = 50
+
+[[alarm]]
+time = "07:00:00"
+audio = "/a.ogg"
+"#, &Settings::default());
+
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(settings.timezone, Zone::Named(::chrono_tz::Asia::Tokyo));
+    assert_eq!(alarms[0].timezone, Zone::Named(::chrono_tz::Asia::Tokyo));
+    assert_eq!(alarms[0].volume, 50);
+    let messages: Vec<String> = loader.diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect();
+    assert_eq!(messages, vec!["config.toml:3: error: Invalid setting: Unknown setting: altitude=35".to_string()]);
+}
+
+#[test]
+fn test_parse_defaults_and_templates() {
+    use chrono::NaiveDate;
+
+    let alarms = parse_content(r#"
+[defaults]
+audio = "/home/me/default.ogg"
+volume = 50
+snooze = 5
+
+[templates.weekday]
+days = "MTWTF__"
+audio = "/home/me/weekday.ogg"
+fade = 30
+device = "USB Speaker"
+
+[[alarm]]
+time = "09:00:00"
+enabled = false
+from = 2026-09-01
+to = "2027-07-03"
+count = 3
+
+[[alarm]]
+template = "weekday"
+time = 06:30:00
+volume = 80
+fade = 10
+
+[[alarm]]
+template = "weekend"
+time = "10:00:00"
+
+[[alarm]]
+time = "09:00..18:00"
+every = "45m"
+"#);
+
+    assert_eq!(alarms.len(), 3);
+
+    assert_eq!(alarms[0].days, vec![true; 7]);
+    assert_eq!(alarms[0].audio_file, "/home/me/default.ogg");
+    assert_eq!(alarms[0].volume, 50);
+    assert_eq!(alarms[0].snooze, 5);
+    assert!(alarms[0].disabled);
+    assert_eq!((alarms[0].first_day, alarms[0].last_day), (NaiveDate::from_ymd_opt(2026, 9, 1), NaiveDate::from_ymd_opt(2027, 7, 3)));
+    assert_eq!(alarms[0].count, Some(3));
+    assert!(!alarms[1].disabled);
+
+    assert_eq!(alarms[1].days, vec![true, true, true, true, true, false, false]);
+    assert_eq!(alarms[1].audio_file, "/home/me/weekday.ogg");
+    assert_eq!(alarms[1].volume, 80);
+    assert_eq!((alarms[1].snooze, alarms[1].fade), (5, 10));
+    assert_eq!(alarms[1].device, Some("USB Speaker".to_string()));
+
+    assert!(alarms[2].is_reminder());
+    assert_eq!(alarms[2].audio_file, "/home/me/default.ogg");
+    assert_eq!(alarms[2].volume, 50);
+
+    assert!(parse_content("[defaults]
+time = \"07:00:00\"
+[[alarm]]
+audio = \"/a.ogg\"").is_empty());
+}
@@ -0,0 +1,145 @@
+use pomodoro;
+use runner;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+
+/// Commented configuration written by `init`
+pub const EXAMPLE_CONFIG: &str = "# Rustine alarms, one per line: days;time;audio;volume[;key=value...]
+#
+# Days: MTWTF__, Mon-Fri, Mon,Wed,Fri, weekdays, weekends, daily
+# Times: 06:30, 6:30am, 18h30, a window 07:00..07:10,
+#        sunrise-00:20 or sunset+00:10 with the latitude and longitude settings
+# Options: label, message, snooze (minutes), fade (seconds), device, timezone, template,
+#          calendar, category, before
+# Relative paths are relative to this file, ~ and $VARIABLES are expanded
+# Files of the conf.d directory next to this file are read too
+
+# Defaults of the alarms
+audio=sounds/chime.wav
+volume=80
+snooze=9
+
+# Wake up on weekdays
+Mon-Fri;06:30;;;label=Wake up
+
+# Sleep in on weekends, remove the # to enable it
+# weekends;09:00;;60;label=Weekend
+";
+
+/// Path of the sample audio file, relative to the configuration directory
+pub const SAMPLE_AUDIO: &str = "sounds/chime.wav";
+
+/// Short two-tone chime, as a 16-bit mono WAV file
+pub fn chime_wav() -> Vec<u8> {
+    tones_wav(&[(880f32, 0.4f32), (660f32, 0.6f32)])
+}
+
+/// Tones given by frequency and duration in seconds played one after the other, as a 16-bit mono WAV file
+pub fn tones_wav(tones: &[(f32, f32)]) -> Vec<u8> {
+    let rate: u32 = 22050;
+
+    let mut samples: Vec<i16> = vec![];
+    for &(frequency, duration) in tones.iter() {
+        let count = (rate as f32 * duration) as usize;
+        for index in 0..count {
+            let t = index as f32 / rate as f32;
+            let envelope = 1f32 - index as f32 / count as f32;
+            samples.push(((2f32 * PI * frequency * t).sin() * envelope * 0.5 * i16::MAX as f32) as i16);
+        }
+    }
+
+    let data_size = (samples.len() * 2) as u32;
+    let mut wav: Vec<u8> = vec![];
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes()); // format chunk size
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&rate.to_le_bytes());
+    wav.extend_from_slice(&(rate * 2).to_le_bytes()); // bytes per second
+    wav.extend_from_slice(&2u16.to_le_bytes()); // bytes per frame
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+/// Result of a step of `init`
+pub struct Check {
+    pub name: String,
+    pub result: Result<String, String>
+}
+
+/// Writes a file unless it already exists
+pub fn create_file(path: &Path, content: &[u8]) -> Result<String, String> {
+    if path.exists() {
+        return Ok(format!("{} already exists, kept", path.display()));
+    }
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|err| format!("Unable to create {}: {}", directory.display(), err))?;
+    }
+    fs::write(path, content).map_err(|err| format!("Unable to write {}: {}", path.display(), err))?;
+    Ok(format!("{} created", path.display()))
+}
+
+/// Creates the configuration file with a commented example, a sample audio file and the sounds of the pomodoro phases,
+/// keeping existing files, then checks that the sample audio decodes and that an audio device is available
+pub fn init(config_file: &Path) -> Vec<Check> {
+    let directory = config_file.parent().unwrap_or_else(|| Path::new(""));
+    let sample = directory.join(SAMPLE_AUDIO);
+
+    let mut checks = vec![
+        Check { name: "Configuration".to_string(), result: create_file(config_file, EXAMPLE_CONFIG.as_bytes()) },
+        Check { name: "Sample audio".to_string(), result: create_file(&sample, &chime_wav()) }
+    ];
+
+    let sample_path = sample.to_string_lossy();
+    checks.push(Check {
+        name: "Sample decoding".to_string(),
+        result: runner::check_audio(&sample_path).map(|_| format!("{} decodes", sample_path))
+    });
+    checks.push(Check {
+        name: "Pomodoro sounds".to_string(),
+        result: pomodoro::sounds().iter()
+            .map(|(audio, content)| create_file(&directory.join(audio), content))
+            .collect::<Result<Vec<String>, String>>()
+            .map(|created| created.join(", "))
+    });
+    checks.push(Check {
+        name: "Audio output".to_string(),
+        result: runner::check_device(None).map(|device| format!("{} is available", device))
+    });
+
+    checks
+}
+
+#[test]
+fn test_init() {
+    let directory = ::tests::TempDir::new("init");
+    let config_file = directory.join("config");
+
+    let checks = init(&config_file);
+    assert!(checks[0].result.is_ok());
+    assert!(checks[1].result.is_ok());
+    assert!(checks[2].result.is_ok());
+    assert!(checks[3].result.is_ok());
+    assert_eq!(fs::read_to_string(&config_file).unwrap(), EXAMPLE_CONFIG);
+    assert!(::pomodoro::check_sounds(&directory.path).is_ok());
+
+    // the example is valid and uses the sample audio
+    let (alarms, diagnostics) = ::config::load_configuration(config_file.to_str().unwrap());
+    assert!(diagnostics.is_empty());
+    assert_eq!(alarms.len(), 1);
+    assert!(alarms[0].audio_file.ends_with(SAMPLE_AUDIO));
+
+    // existing files are kept
+    fs::write(&config_file, "weekends;09:00;/a.ogg;60").unwrap();
+    let checks = init(&config_file);
+    assert_eq!(checks[0].result, Ok(format!("{} already exists, kept", config_file.display())));
+    assert_eq!(fs::read_to_string(&config_file).unwrap(), "weekends;09:00;/a.ogg;60");
+}
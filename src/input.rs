@@ -2,19 +2,59 @@ use std::sync::mpsc::Sender;
 use std::thread;
 use std::thread::JoinHandle;
 use config::AlarmConfig;
+use runner::Sound;
 
 /// Messages that are sent in the channels
 pub enum Message {
-    /// Audio file path and output volume in percent [0, 100]
-    AudioAndVolume(String, u32),
+    /// Sound of the alarm to play
+    Play(Sound),
+    /// Short sound to play only when no other sound is playing
+    Chime(Sound),
     /// New alarm configuration list
     Reconfigure(Vec<AlarmConfig>),
     /// Request help message
     Help,
     /// Show configuration
     Show,
+    /// Show the configuration of an alarm, given by label or position
+    ShowAlarm(String),
+    /// Show the next alarm to run
+    Next,
     /// Notify to stop the running alarm
     StopAlarm,
+    /// Stop the running alarm only when it is the given one
+    StopNamedAlarm(String),
+    /// Stop the running alarm and ring it again after its snooze duration
+    Snooze,
+    /// Add an alarm, written as a line of the CSV-like format, to the configuration file
+    Add(String),
+    /// Change fields of an alarm given by label or position, written `key=value;key=value`
+    Edit(String, String),
+    /// Remove an alarm given by label or position from the configuration file
+    Remove(String),
+    /// Enable an alarm given by label or position in the configuration file
+    Enable(String),
+    /// Disable an alarm given by label or position in the configuration file, it is kept but does not run
+    Disable(String),
+    /// Skip the next run of an alarm given by label or position
+    Skip(String),
+    /// Move the next run of an alarm given by label or position to another time of the same day
+    Override(String, String),
+    /// Disable an alarm, given by its state key, that reached its count of runs
+    /// Sent by the alarm manager, so that the configuration files are only written by the main thread
+    CountReached(String),
+    /// Pause all the alarms until a day written `YYYY-MM-DD`
+    Pause(String),
+    /// Remove the pause of the alarms
+    Resume,
+    /// Start a timer, written `<duration> [label] [audio]`
+    Timer(String),
+    /// Show the running timers
+    Timers,
+    /// Drop a timer given by number or label
+    Cancel(String),
+    /// Start, show, pause, resume, skip the phase of or stop the pomodoro, see `pomodoro::parse_action`
+    Pomodoro(String),
     /// Stop the application
     Quit
 }
@@ -28,15 +68,118 @@ impl Message {
     /// Stringify some messages to get the user input expectation
     pub fn as_str(&self) -> &str {
         match self {
-            &Message::StopAlarm => "stop",
+            &Message::StopAlarm | &Message::StopNamedAlarm(_) => "stop",
             &Message::Quit => "quit",
-            &Message::Show => "show",
+            &Message::Show | &Message::ShowAlarm(_) => "show",
+            &Message::Next => "next",
+            &Message::Snooze => "snooze",
+            &Message::Add(_) => "add",
+            &Message::Edit(_, _) => "edit",
+            &Message::Remove(_) => "remove",
+            &Message::Enable(_) => "enable",
+            &Message::Disable(_) => "disable",
+            &Message::Skip(_) => "skip",
+            &Message::Override(_, _) => "override",
+            &Message::Pause(_) => "pause",
+            &Message::Resume => "resume",
+            &Message::Timer(_) => "timer",
+            &Message::Timers => "timers",
+            &Message::Cancel(_) => "cancel",
+            &Message::Pomodoro(_) => "pomodoro",
             &Message::Help => "help",
             _ => "other_message"
         }
     }
 }
 
+/// Splits the `<alarm> key=value;key=value` argument of the edit command,
+/// the alarm being the words before the first `key=value` field
+fn split_edit(argument: &str) -> Option<(String, String)> {
+    argument.char_indices()
+        .filter(|&(_, c)| c.is_whitespace())
+        .map(|(index, _)| (argument[..index].trim(), argument[index..].trim()))
+        .find(|&(_, changes)| changes.split_whitespace().next().is_some_and(|word| word.contains('=')))
+        .map(|(alarm, changes)| (alarm.to_string(), changes.to_string()))
+}
+
+/// Parses a user input line to a message, None when the command is unknown
+/// Commands are case insensitive and may be followed by an alarm label or position
+/// # Examples
+/// ```
+/// use rustine::input::{parse_command, Message};
+/// assert_eq!(parse_command("Show").map(|m| m.as_str().to_string()), Some("show".to_string()));
+/// assert!(parse_command("dance").is_none());
+/// ```
+pub fn parse_command(input: &str) -> Option<Message> {
+    let input = input.trim();
+    let (command, argument) = match input.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command.to_lowercase(), Some(argument.trim().to_string())),
+        None => (input.to_lowercase(), None)
+    };
+
+    match (command.as_str(), argument) {
+        ("quit", None) => Some(Message::Quit),
+        ("stop", None) => Some(Message::StopAlarm),
+        ("stop", Some(alarm)) => Some(Message::StopNamedAlarm(alarm)),
+        ("show", None) => Some(Message::Show),
+        ("show", Some(alarm)) => Some(Message::ShowAlarm(alarm)),
+        ("next", None) => Some(Message::Next),
+        ("snooze", None) => Some(Message::Snooze),
+        ("add", Some(line)) => Some(Message::Add(line)),
+        ("edit", Some(argument)) => split_edit(&argument).map(|(alarm, changes)| Message::Edit(alarm, changes)),
+        ("remove", Some(alarm)) => Some(Message::Remove(alarm)),
+        ("enable", Some(alarm)) => Some(Message::Enable(alarm)),
+        ("disable", Some(alarm)) => Some(Message::Disable(alarm)),
+        ("skip", Some(alarm)) => Some(Message::Skip(alarm)),
+        ("override", Some(argument)) => argument.rsplit_once(char::is_whitespace)
+            .map(|(alarm, time)| Message::Override(alarm.trim().to_string(), time.to_string())),
+        ("pause", Some(argument)) => {
+            let until = argument.strip_prefix("until").filter(|date| date.starts_with(char::is_whitespace)).unwrap_or(&argument);
+            Some(Message::Pause(until.trim().to_string()))
+        },
+        ("resume", None) => Some(Message::Resume),
+        ("timer", Some(argument)) => Some(Message::Timer(argument)),
+        ("timers", None) => Some(Message::Timers),
+        ("cancel", Some(timer)) => Some(Message::Cancel(timer)),
+        ("pomodoro", argument) => Some(Message::Pomodoro(argument.unwrap_or_default())),
+        ("help", None) => Some(Message::Help),
+        _ => None
+    }
+}
+
+#[test]
+fn test_parse_command() {
+    assert!(matches!(parse_command("quit\n"), Some(Message::Quit)));
+    assert!(matches!(parse_command("  STOP "), Some(Message::StopAlarm)));
+    assert!(matches!(parse_command("stop Wake up"), Some(Message::StopNamedAlarm(ref alarm)) if alarm == "Wake up"));
+    assert!(matches!(parse_command("show 2"), Some(Message::ShowAlarm(ref alarm)) if alarm == "2"));
+    assert!(matches!(parse_command("next"), Some(Message::Next)));
+    assert!(parse_command("next week").is_none());
+    assert!(matches!(parse_command("snooze"), Some(Message::Snooze)));
+    assert!(matches!(parse_command("add MTWTF__;06:30;/a.ogg;75"), Some(Message::Add(ref line)) if line == "MTWTF__;06:30;/a.ogg;75"));
+    assert!(matches!(parse_command("edit Wake up time=6:45; label=Wake up early"),
+        Some(Message::Edit(ref alarm, ref changes)) if alarm == "Wake up" && changes == "time=6:45; label=Wake up early"));
+    assert!(parse_command("edit 2").is_none());
+    assert!(matches!(parse_command("remove 2"), Some(Message::Remove(ref alarm)) if alarm == "2"));
+    assert!(parse_command("remove").is_none());
+    assert!(matches!(parse_command("Disable Gym"), Some(Message::Disable(ref alarm)) if alarm == "Gym"));
+    assert!(matches!(parse_command("enable 1"), Some(Message::Enable(ref alarm)) if alarm == "1"));
+    assert!(matches!(parse_command("skip Wake up"), Some(Message::Skip(ref alarm)) if alarm == "Wake up"));
+    assert!(matches!(parse_command("override Wake up 05:45"),
+        Some(Message::Override(ref alarm, ref time)) if alarm == "Wake up" && time == "05:45"));
+    assert!(parse_command("override 05:45").is_none());
+    assert!(matches!(parse_command("pause until 2026-11-02"), Some(Message::Pause(ref until)) if until == "2026-11-02"));
+    assert!(matches!(parse_command("pause 2026-11-02"), Some(Message::Pause(ref until)) if until == "2026-11-02"));
+    assert!(matches!(parse_command("resume"), Some(Message::Resume)));
+    assert!(matches!(parse_command("timer 25m Tea"), Some(Message::Timer(ref argument)) if argument == "25m Tea"));
+    assert!(parse_command("timer").is_none());
+    assert!(matches!(parse_command("timers"), Some(Message::Timers)));
+    assert!(matches!(parse_command("cancel 2"), Some(Message::Cancel(ref timer)) if timer == "2"));
+    assert!(matches!(parse_command("pomodoro"), Some(Message::Pomodoro(ref argument)) if argument.is_empty()));
+    assert!(matches!(parse_command("Pomodoro 50m 10m"), Some(Message::Pomodoro(ref argument)) if argument == "50m 10m"));
+    assert!(parse_command("").is_none());
+}
+
 /// Thread to watch user keyboard inputs
 pub fn watch_input(tx_keyboard_input: Sender<Message>) -> JoinHandle<()> {
     let join_handle = thread::spawn(move || {
@@ -46,28 +189,58 @@ pub fn watch_input(tx_keyboard_input: Sender<Message>) -> JoinHandle<()> {
             let mut buffer = String::new();
             stdin().read_line(&mut buffer).expect("Did not enter a correct string");
 
-            let forward = buffer.to_lowercase().trim().to_string();
-
-            if forward != "" {
-                if forward == Message::Quit.as_str() {
+            match parse_command(&buffer) {
+                Some(Message::Quit) => {
                     let _send_result = tx_keyboard_input.send(Message::Quit);
                     break;
-                } else if forward == Message::StopAlarm.as_str() {
-                    let _send_result = tx_keyboard_input.send(Message::StopAlarm);
-                } else if forward == Message::Show.as_str() {
-                    let _send_result = tx_keyboard_input.send(Message::Show);
-                } else if forward == Message::Help.as_str() {
+                },
+                Some(Message::Help) => {
                     println!("\n
 '{}' shows this message
-'{}' shows the loaded configuration
-'{}' stops the running alarm
+'{} [alarm]' shows the loaded configuration, or a single alarm given by label or position
+'{}' shows the next alarm to run
+'{} [alarm]' stops the running alarm, or only the given alarm
+'{}' stops the running alarm and rings it again after its snooze duration
+'{} <days;time;audio;volume[;key=value...]>' adds an alarm to the configuration file
+'{} <alarm> <key=value[;key=value...]>' changes fields of an alarm, an empty value removes the field
+'{} <alarm>' removes an alarm from the configuration file
+'{} <alarm>' and '{} <alarm>' enable and disable an alarm, disabled alarms are kept but do not run
+'{} <alarm>' skips the next run of an alarm
+'{} <alarm> <time>' moves the next run of an alarm to another time of the same day
+'{} until <YYYY-MM-DD>' pauses all the alarms until the given day, '{}' runs them again
+'{} <duration> [label] [audio]' starts a timer, such as '{} 25m Tea', the sample chime rings when no audio file is given
+'{}' shows the running timers with their remaining time, '{} <timer>' drops a timer given by number or label
+'{} [work] [short break] [long break] [cycles]' starts work and break phases, 25m, 5m, 15m and a long break every 4 work sessions by default
+'{} status|pause|resume|skip|stop' shows, pauses, resumes, ends the current phase of or stops the pomodoro
 '{}' stops the application\n",
                         Message::Help.as_str(),
                         Message::Show.as_str(),
+                        Message::Next.as_str(),
                         Message::StopAlarm.as_str(),
+                        Message::Snooze.as_str(),
+                        Message::Add(String::new()).as_str(),
+                        Message::Edit(String::new(), String::new()).as_str(),
+                        Message::Remove(String::new()).as_str(),
+                        Message::Enable(String::new()).as_str(),
+                        Message::Disable(String::new()).as_str(),
+                        Message::Skip(String::new()).as_str(),
+                        Message::Override(String::new(), String::new()).as_str(),
+                        Message::Pause(String::new()).as_str(),
+                        Message::Resume.as_str(),
+                        Message::Timer(String::new()).as_str(),
+                        Message::Timer(String::new()).as_str(),
+                        Message::Timers.as_str(),
+                        Message::Cancel(String::new()).as_str(),
+                        Message::Pomodoro(String::new()).as_str(),
+                        Message::Pomodoro(String::new()).as_str(),
                         Message::Quit.as_str()
                     )
-                }
+                },
+                Some(message) => {
+                    let _send_result = tx_keyboard_input.send(message);
+                },
+                None if buffer.trim().is_empty() => {},
+                None => println!("Unknown command, '{}' shows the supported commands", Message::Help.as_str())
             }
         }
     });
@@ -1,9 +1,29 @@
+// the baseline tests pass owned strings and compare booleans with assert_eq
+#![cfg_attr(test, allow(clippy::unnecessary_to_owned, clippy::bool_assert_comparison))]
+
 extern crate chrono;
+extern crate chrono_tz;
 extern crate rodio;
 extern crate notify;
+extern crate rand;
+extern crate toml;
+extern crate toml_edit;
+#[macro_use] extern crate serde_json;
 
+pub mod calendar;
+pub mod check;
+pub mod cli;
 pub mod config;
+pub mod init;
 pub mod input;
+pub mod paths;
+pub mod pomodoro;
 pub mod runner;
+pub mod scheduler;
+pub mod simulate;
+pub mod solar;
+pub mod state;
+pub mod timer;
+pub mod zone;
 
 #[cfg(test)] mod tests;
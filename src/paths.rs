@@ -0,0 +1,184 @@
+use notify::DebouncedEvent;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Expands a path written in a configuration file:
+/// - a leading `~` is the home directory
+/// - `$NAME` and `${NAME}` are environment variables
+/// - relative paths are relative to the given directory
+pub fn expand(path: &str, base: &Path) -> Result<String, String> {
+    expand_with(path, base, |name| env::var(name).ok())
+}
+
+/// Expands a path, reading the variables with the given function
+fn expand_with<F: Fn(&str) -> Option<String>>(path: &str, base: &Path, var: F) -> Result<String, String> {
+    let mut expanded = String::new();
+
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            expanded.push_str(&var("HOME").ok_or("Unable to expand ~, HOME is not set")?);
+            rest
+        },
+        _ => path
+    };
+
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+        }
+        let mut name = String::new();
+        while let Some(&next) = chars.peek() {
+            if !(next.is_ascii_alphanumeric() || next == '_') {
+                break;
+            }
+            name.push(next);
+            chars.next();
+        }
+        if braced && chars.next() != Some('}') {
+            return Err(format!("Invalid variable in path: {}", path));
+        }
+
+        if name.is_empty() {
+            // a `$` alone is kept
+            expanded.push('$');
+        } else {
+            expanded.push_str(&var(&name).ok_or(format!("Unknown environment variable {} in path: {}", name, path))?);
+        }
+    }
+
+    if Path::new(&expanded).is_relative() {
+        expanded = base.join(&expanded).to_string_lossy().into_owned();
+    }
+    Ok(expanded)
+}
+
+/// Directory of a configuration file, used to resolve the relative paths it contains
+pub fn directory_of(file: &Path) -> PathBuf {
+    let directory = file.parent().unwrap_or_else(|| Path::new(""));
+    directory.canonicalize().unwrap_or_else(|_| directory.to_path_buf())
+}
+
+/// Configuration file to read, the first of:
+/// - the file given on the command line
+/// - the file given by the `RUSTINE_CONFIG` environment variable
+/// - `$XDG_CONFIG_HOME/rustine/config.toml` or `config` when it exists, `XDG_CONFIG_HOME` defaulting to `~/.config`
+/// - `./rustine_config/config.toml` or `config` when it exists
+/// - `$XDG_CONFIG_HOME/rustine/config` otherwise
+pub fn config_file(argument: Option<&str>) -> PathBuf {
+    config_file_with(argument, |name| env::var(name).ok(), |path| path.exists())
+}
+
+/// Finds the configuration file, reading the variables and checking that files exist with the given functions
+fn config_file_with<F, E>(argument: Option<&str>, var: F, exists: E) -> PathBuf
+    where F: Fn(&str) -> Option<String>, E: Fn(&Path) -> bool {
+    if let Some(file) = argument {
+        return PathBuf::from(file);
+    }
+    if let Some(file) = var("RUSTINE_CONFIG").filter(|file| !file.is_empty()) {
+        return PathBuf::from(expand_with(&file, Path::new(""), &var).unwrap_or(file));
+    }
+
+    let xdg_home = var("XDG_CONFIG_HOME")
+        .filter(|home| Path::new(home).is_absolute())
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| Path::new(&home).join(".config")));
+    let xdg_directory = xdg_home.map(|home| home.join("rustine"));
+
+    let candidates = xdg_directory.iter()
+        .chain(Some(PathBuf::from("./rustine_config")).iter())
+        .flat_map(|directory| vec![directory.join("config.toml"), directory.join("config")])
+        .collect::<Vec<_>>();
+
+    candidates.into_iter()
+        .find(|candidate| exists(candidate))
+        .or_else(|| xdg_directory.map(|directory| directory.join("config")))
+        .unwrap_or_else(|| PathBuf::from("./rustine_config/config"))
+}
+
+/// Checks if a file system event concerns the configuration file or its conf.d directory
+pub fn concerns_configuration(event: &DebouncedEvent, config_files: &[PathBuf], conf_d: &Path) -> bool {
+    let concerns = |path: &PathBuf| config_files.contains(path) || path.starts_with(conf_d);
+    match *event {
+        DebouncedEvent::Create(ref path) | DebouncedEvent::Write(ref path) | DebouncedEvent::Remove(ref path) => concerns(path),
+        DebouncedEvent::Rename(ref from, ref to) => concerns(from) || concerns(to),
+        DebouncedEvent::Rescan => true,
+        _ => false
+    }
+}
+
+/// Files whose changes reload the configuration: the configuration file as named in its watched `directory`,
+/// and the file it links to when it is a symbolic link, such as a file of a dotfiles repository
+pub fn configuration_files(config_file: &Path, directory: &Path) -> Vec<PathBuf> {
+    let mut files = vec![directory.join(config_file.file_name().unwrap_or_default())];
+    if let Ok(target) = fs::canonicalize(config_file) {
+        if !files.contains(&target) {
+            files.push(target);
+        }
+    }
+    files
+}
+
+/// File keeping the state of the alarms between runs, next to the configuration file: `config.state` for `config`
+pub fn state_file(config_file: &Path) -> PathBuf {
+    let mut name = config_file.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(".state");
+    config_file.with_file_name(name)
+}
+
+#[test]
+fn test_state_file() {
+    assert_eq!(state_file(Path::new("/home/me/.config/rustine/config")), PathBuf::from("/home/me/.config/rustine/config.state"));
+    assert_eq!(state_file(Path::new("alarms.toml")), PathBuf::from("alarms.toml.state"));
+}
+
+#[test]
+fn test_expand() {
+    let var = |name: &str| match name {
+        "HOME" => Some("/home/me".to_string()),
+        "SOUNDS" => Some("/usr/share/sounds".to_string()),
+        _ => None
+    };
+    let base = Path::new("/etc/rustine");
+
+    assert_eq!(expand_with("/music/bell.ogg", base, var), Ok("/music/bell.ogg".to_string()));
+    assert_eq!(expand_with("~/sounds/bell.ogg", base, var), Ok("/home/me/sounds/bell.ogg".to_string()));
+    assert_eq!(expand_with("$HOME/bell.ogg", base, var), Ok("/home/me/bell.ogg".to_string()));
+    assert_eq!(expand_with("${SOUNDS}/bell_${HOME}.ogg", base, var), Ok("/usr/share/sounds/bell_/home/me.ogg".to_string()));
+    assert_eq!(expand_with("sounds/bell.ogg", base, var), Ok("/etc/rustine/sounds/bell.ogg".to_string()));
+    assert_eq!(expand_with("~other/bell.ogg", base, var), Ok("/etc/rustine/~other/bell.ogg".to_string()));
+    assert_eq!(expand_with("/music/$ 5.ogg", base, var), Ok("/music/$ 5.ogg".to_string()));
+    assert!(expand_with("$MUSIC/bell.ogg", base, var).is_err());
+    assert!(expand_with("${SOUNDS/bell.ogg", base, var).is_err());
+}
+
+#[test]
+fn test_config_file() {
+    let var = |name: &str| match name {
+        "HOME" => Some("/home/me".to_string()),
+        _ => None
+    };
+    let none = |_: &Path| false;
+
+    assert_eq!(config_file_with(Some("/tmp/alarms"), var, none), PathBuf::from("/tmp/alarms"));
+    assert_eq!(config_file_with(None, |name: &str| match name {
+        "RUSTINE_CONFIG" => Some("~/alarms.toml".to_string()),
+        _ => var(name)
+    }, none), PathBuf::from("/home/me/alarms.toml"));
+
+    assert_eq!(config_file_with(None, var, none), PathBuf::from("/home/me/.config/rustine/config"));
+    assert_eq!(config_file_with(None, var, |path: &Path| path == Path::new("./rustine_config/config")), PathBuf::from("./rustine_config/config"));
+    assert_eq!(config_file_with(None, var, |path: &Path| path.ends_with("config.toml")), PathBuf::from("/home/me/.config/rustine/config.toml"));
+    assert_eq!(config_file_with(None, |name: &str| match name {
+        "XDG_CONFIG_HOME" => Some("/etc/xdg".to_string()),
+        _ => var(name)
+    }, none), PathBuf::from("/etc/xdg/rustine/config"));
+    assert_eq!(config_file_with(None, |_: &str| None, none), PathBuf::from("./rustine_config/config"));
+}
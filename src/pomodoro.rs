@@ -0,0 +1,266 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use init;
+use runner;
+use runner::Sound;
+use std::path::Path;
+use timer;
+
+/// Phase of a pomodoro
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak
+}
+
+impl Phase {
+
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Phase::Work => "work",
+            Phase::ShortBreak => "short break",
+            Phase::LongBreak => "long break"
+        }
+    }
+
+    /// Audio file played when the phase starts, relative to the configuration directory
+    fn audio(&self) -> &'static str {
+        match *self {
+            Phase::Work => "sounds/work.wav",
+            Phase::ShortBreak => "sounds/short-break.wav",
+            Phase::LongBreak => "sounds/long-break.wav"
+        }
+    }
+
+    /// Tones of the audio file, rising for work and falling for the breaks
+    fn tones(&self) -> &'static [(f32, f32)] {
+        match *self {
+            Phase::Work => &[(523.25, 0.2), (659.25, 0.2), (783.99, 0.4)],
+            Phase::ShortBreak => &[(783.99, 0.3), (523.25, 0.5)],
+            Phase::LongBreak => &[(783.99, 0.3), (659.25, 0.3), (523.25, 0.3), (392.0, 0.8)]
+        }
+    }
+}
+
+/// Durations of the phases, with the number of work sessions before a long break
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub work: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+    pub cycles: u32
+}
+
+impl Default for Plan {
+    fn default() -> Plan {
+        Plan { work: Duration::minutes(25), short_break: Duration::minutes(5), long_break: Duration::minutes(15), cycles: 4 }
+    }
+}
+
+impl Plan {
+
+    /// Duration of a phase
+    pub fn duration(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Work => self.work,
+            Phase::ShortBreak => self.short_break,
+            Phase::LongBreak => self.long_break
+        }
+    }
+}
+
+/// Action of the pomodoro command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Starts a pomodoro, with the default plan when not given, or shows the running one
+    Start(Option<Plan>),
+    Status,
+    Pause,
+    Resume,
+    /// Ends the current phase and starts the next one
+    Skip,
+    Stop
+}
+
+/// Reads the argument of the pomodoro command: an action,
+/// or `[work] [short break] [long break] [cycles]` to start with other durations than 25m, 5m, 15m and 4 cycles
+pub fn parse_action(argument: &str) -> Result<Action, String> {
+    match argument.trim().to_lowercase().as_str() {
+        "" => return Ok(Action::Start(None)),
+        "status" => return Ok(Action::Status),
+        "pause" => return Ok(Action::Pause),
+        "resume" => return Ok(Action::Resume),
+        "skip" => return Ok(Action::Skip),
+        "stop" => return Ok(Action::Stop),
+        _ => {}
+    }
+
+    let words: Vec<&str> = argument.split_whitespace().collect();
+    if words.len() > 4 {
+        return Err("expected [work] [short break] [long break] [cycles]".to_string());
+    }
+    let mut plan = Plan::default();
+    for (index, word) in words.iter().enumerate() {
+        match index {
+            0 => plan.work = timer::parse_duration(word)?,
+            1 => plan.short_break = timer::parse_duration(word)?,
+            2 => plan.long_break = timer::parse_duration(word)?,
+            _ => plan.cycles = word.parse().ok().filter(|cycles| *cycles > 0).ok_or(format!("Invalid number of cycles: {}", word))?
+        }
+    }
+    Ok(Action::Start(Some(plan)))
+}
+
+#[test]
+fn test_parse_action() {
+    assert_eq!(parse_action(""), Ok(Action::Start(None)));
+    assert_eq!(parse_action(" Pause "), Ok(Action::Pause));
+    assert_eq!(parse_action("skip"), Ok(Action::Skip));
+    assert_eq!(parse_action("50m 10m"), Ok(Action::Start(Some(Plan { work: Duration::minutes(50), short_break: Duration::minutes(10), ..Plan::default() }))));
+    assert_eq!(parse_action("25 5 20 3"), Ok(Action::Start(Some(Plan { long_break: Duration::minutes(20), cycles: 3, ..Plan::default() }))));
+    assert!(parse_action("25m 5m 15m 0").is_err());
+    assert!(parse_action("25m 5m 15m 4 2").is_err());
+    assert!(parse_action("later").is_err());
+}
+
+/// Audio files of the phases, relative to the configuration directory, with their content, created by `init`
+pub fn sounds() -> Vec<(&'static str, Vec<u8>)> {
+    [Phase::Work, Phase::ShortBreak, Phase::LongBreak].iter()
+        .map(|phase| (phase.audio(), init::tones_wav(phase.tones())))
+        .collect()
+}
+
+/// Checks that the audio files of the phases play from the configuration directory `base`
+pub fn check_sounds(base: &Path) -> Result<(), String> {
+    for phase in [Phase::Work, Phase::ShortBreak, Phase::LongBreak].iter() {
+        runner::check_audio(&base.join(phase.audio()).to_string_lossy())
+            .map_err(|err| format!("{}, init creates the pomodoro sounds", err))?;
+    }
+    Ok(())
+}
+
+/// Running pomodoro, going through its phases until it is stopped
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pomodoro {
+    pub plan: Plan,
+    pub phase: Phase,
+    /// Number of the current or last work session, starting at 1
+    pub session: u32,
+    /// Time at which the current phase ends when it is not paused
+    end: DateTime<Local>,
+    /// Time at which the pomodoro was paused
+    paused: Option<DateTime<Local>>
+}
+
+impl Pomodoro {
+
+    /// Starts with a work session, an error when a phase is too long
+    pub fn start(plan: Plan, now: &DateTime<Local>) -> Result<Pomodoro, String> {
+        for phase in [Phase::ShortBreak, Phase::LongBreak].iter() {
+            timer::end_after(now, plan.duration(*phase))?;
+        }
+        let end = timer::end_after(now, plan.work)?;
+        Ok(Pomodoro { plan, phase: Phase::Work, session: 1, end, paused: None })
+    }
+
+    /// Time left in the current phase
+    pub fn remaining(&self, now: &DateTime<Local>) -> Duration {
+        self.end - self.paused.unwrap_or(*now)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    pub fn pause(&mut self, now: &DateTime<Local>) -> Result<(), String> {
+        if self.paused.is_some() {
+            return Err("The pomodoro is already paused".to_string());
+        }
+        self.paused = Some(*now);
+        Ok(())
+    }
+
+    /// Runs again, the current phase ending later by the time spent paused
+    pub fn resume(&mut self, now: &DateTime<Local>) -> Result<(), String> {
+        let paused = self.paused.take().ok_or("The pomodoro is not paused")?;
+        self.end += *now - paused;
+        Ok(())
+    }
+
+    /// Starts the next phase, a long break after every `cycles` work sessions, it stays paused when it was
+    pub fn next(&mut self, now: &DateTime<Local>) -> Phase {
+        self.phase = match self.phase {
+            Phase::Work if self.session.is_multiple_of(self.plan.cycles) => Phase::LongBreak,
+            Phase::Work => Phase::ShortBreak,
+            Phase::ShortBreak | Phase::LongBreak => {
+                self.session += 1;
+                Phase::Work
+            }
+        };
+        // the durations were checked when the pomodoro started
+        self.end = timer::end_after(now, self.plan.duration(self.phase)).unwrap_or(*now);
+        if self.paused.is_some() {
+            self.paused = Some(*now);
+        }
+        self.phase
+    }
+
+    /// Starts the next phase when the current one is over, and returns it
+    pub fn tick(&mut self, now: &DateTime<Local>) -> Option<Phase> {
+        if self.paused.is_none() && *now >= self.end {
+            Some(self.next(now))
+        } else {
+            None
+        }
+    }
+
+    /// Sound played when the current phase starts, from the configuration directory `base`
+    pub fn sound(&self, base: &Path) -> Sound {
+        Sound { audio_file: base.join(self.phase.audio()).to_string_lossy().into_owned(), volume: 100 }
+    }
+
+    /// Current phase with its remaining time, such as `work 2/4, 12:34 left`
+    pub fn status(&self, now: &DateTime<Local>) -> String {
+        let session = (self.session - 1) % self.plan.cycles + 1;
+        format!("{} {}/{}, {} left{}",
+            self.phase.as_str(),
+            session,
+            self.plan.cycles,
+            timer::format_remaining(self.remaining(now)),
+            if self.is_paused() { " (paused)" } else { "" })
+    }
+}
+
+#[test]
+fn test_pomodoro() {
+    let now = Local::now();
+    let plan = Plan { cycles: 2, ..Plan::default() };
+    assert!(Pomodoro::start(Plan { long_break: timer::parse_duration("9999999999h").unwrap(), ..Plan::default() }, &now).is_err());
+    let mut pomodoro = Pomodoro::start(plan, &now).unwrap();
+    assert_eq!(pomodoro.status(&(now + Duration::seconds(90))), "work 1/2, 23:30 left");
+
+    // phases follow each other, with a long break after the second work session
+    assert_eq!(pomodoro.tick(&(now + Duration::minutes(24))), None);
+    let now = now + Duration::minutes(25);
+    assert_eq!(pomodoro.tick(&now), Some(Phase::ShortBreak));
+    let now = now + Duration::minutes(5);
+    assert_eq!(pomodoro.tick(&now), Some(Phase::Work));
+    assert_eq!(pomodoro.status(&now), "work 2/2, 25:00 left");
+    assert_eq!(pomodoro.next(&now), Phase::LongBreak);
+    assert_eq!(pomodoro.next(&now), Phase::Work);
+    assert_eq!(pomodoro.status(&now), "work 1/2, 25:00 left");
+
+    // paused time does not count
+    pomodoro.pause(&(now + Duration::minutes(10))).unwrap();
+    assert!(pomodoro.pause(&now).is_err());
+    assert_eq!(pomodoro.tick(&(now + Duration::hours(1))), None);
+    assert_eq!(pomodoro.status(&(now + Duration::hours(1))), "work 1/2, 15:00 left (paused)");
+    assert_eq!(pomodoro.next(&(now + Duration::hours(1))), Phase::ShortBreak);
+    assert_eq!(pomodoro.remaining(&(now + Duration::hours(2))), Duration::minutes(5));
+    pomodoro.resume(&(now + Duration::hours(2))).unwrap();
+    assert!(pomodoro.resume(&now).is_err());
+    assert_eq!(pomodoro.tick(&(now + Duration::hours(2) + Duration::minutes(5))), Some(Phase::Work));
+
+    assert_eq!(pomodoro.sound(Path::new("/etc/rustine")).audio_file, "/etc/rustine/sounds/work.wav");
+}
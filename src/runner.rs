@@ -1,3 +1,4 @@
+use config::AlarmConfig;
 use input::Message;
 use rodio;
 use rodio::Sink;
@@ -8,6 +9,40 @@ use std::sync::mpsc;
 use std::thread;
 use std::thread::JoinHandle;
 
+/// Sound played when an alarm rings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sound {
+    pub audio_file: String,
+    /// Output volume in percent [0, 100]
+    pub volume: u32
+}
+
+impl From<&AlarmConfig> for Sound {
+    fn from(alarm: &AlarmConfig) -> Sound {
+        Sound {
+            audio_file: alarm.audio_file.clone(),
+            volume: alarm.volume
+        }
+    }
+}
+
+/// Checks that an audio file can be opened and decoded
+pub fn check_audio(path: &str) -> Result<(), String> {
+    let file = File::open(path).map_err(|err| format!("Unable to open {}: {}", path, err))?;
+    rodio::Decoder::new(BufReader::new(file))
+        .map(|_| ())
+        .map_err(|err| format!("Unable to decode {}: {}", path, err))
+}
+
+/// Checks that an output device can play, the named device or the default one, and returns its name
+pub fn check_device(name: Option<&str>) -> Result<String, String> {
+    let device = match name {
+        Some(name) => rodio::output_devices().find(|device| device.name() == name).ok_or(format!("Unknown audio device {}", name))?,
+        None => rodio::default_output_device().ok_or("No audio output device")?
+    };
+    device.default_output_format().map_err(|err| format!("Audio device {} can not play: {:?}", device.name(), err))?;
+    Ok(device.name())
+}
 
 /// Runs a thread that manages the audio file
 pub fn start(rx_alarm_runner: mpsc::Receiver<Message>) -> JoinHandle<()> {
@@ -18,12 +53,26 @@ pub fn start(rx_alarm_runner: mpsc::Receiver<Message>) -> JoinHandle<()> {
 
         loop {
             match rx_alarm_runner.recv() {
-                Ok(Message::AudioAndVolume(a_file_path, a_volume)) => {
-                    let file = File::open(a_file_path).unwrap();
-                    let source = rodio::Decoder::new(BufReader::new(file)).unwrap();
+                // a reminder does not cut the sound of an alarm, a timer or a pomodoro
+                Ok(Message::Chime(_)) if !sink.empty() => {},
+                Ok(Message::Play(sound)) | Ok(Message::Chime(sound)) => {
+                    // a file removed or changed since it was checked is skipped, the next sounds still play
+                    let source = match File::open(&sound.audio_file) {
+                        Ok(file) => match rodio::Decoder::new(BufReader::new(file)) {
+                            Ok(source) => source,
+                            Err(err) => {
+                                println!("Unable to decode {}: {}", sound.audio_file, err);
+                                continue;
+                            }
+                        },
+                        Err(err) => {
+                            println!("Unable to open {}: {}", sound.audio_file, err);
+                            continue;
+                        }
+                    };
                     sink = Sink::new(&device);
                     sink.append(source);
-                    sink.set_volume(to_volume(a_volume));
+                    sink.set_volume(to_volume(sound.volume));
                     sink.play(); // should already be ok
                 },
                 Ok(Message::StopAlarm) => {
@@ -48,6 +97,7 @@ pub fn start(rx_alarm_runner: mpsc::Receiver<Message>) -> JoinHandle<()> {
 /// assert_eq!(to_volume(100), 1f32);
 /// assert_eq!(to_volume(50), 0.5f32);
 /// ```
+#[allow(clippy::manual_clamp)]
 pub fn to_volume(v: u32) -> f32 {
     let volume = cmp::min(cmp::max(0, v), 100) as f32;
     volume / 100f32
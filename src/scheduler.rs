@@ -0,0 +1,137 @@
+use chrono::prelude::*;
+use config;
+use config::AlarmConfig;
+
+/// Finds an alarm of a list by label, case insensitive, or by position starting at 1
+pub fn find(alarms: &[AlarmConfig], id: &str) -> Option<usize> {
+    let id_lowercase = id.to_lowercase();
+    alarms.iter()
+        .position(|alarm| alarm.label.as_ref().is_some_and(|label| label.to_lowercase() == id_lowercase))
+        .or_else(|| {
+            id.parse::<usize>().ok()
+                .filter(|position| *position >= 1 && *position <= alarms.len())
+                .map(|position| position - 1)
+        })
+}
+
+/// Keeps the alarm list and decides when alarms have to be launched
+pub struct Scheduler {
+    pub alarms: Vec<AlarmConfig>,
+    /// Offset of the system time zone at the last tick
+    utc_offset: Option<FixedOffset>
+}
+
+impl Scheduler {
+
+    pub fn new(alarms: Vec<AlarmConfig>) -> Scheduler {
+        Scheduler { alarms, utc_offset: None }
+    }
+
+    /// Replaces the alarms by the ones read again from the configuration
+    /// Alarms whose definition did not change keep their last run, not to skip a run due while reloading
+    /// Returns the new position of each previous alarm, found by its key, None when it was removed
+    pub fn reconfigure(&mut self, mut alarms: Vec<AlarmConfig>) -> Vec<Option<usize>> {
+        for alarm in alarms.iter_mut() {
+            if let Some(old) = self.alarms.iter().find(|old| config::same_definition(old, alarm)) {
+                alarm.last_run = old.last_run;
+            }
+        }
+        let positions = self.alarms.iter()
+            .map(|old| {
+                let key = old.key();
+                alarms.iter().position(|alarm| alarm.key() == key)
+            })
+            .collect();
+        self.alarms = alarms;
+        positions
+    }
+
+    /// Returns the positions of the alarms to launch at the given time and marks them as run
+    /// Alarm times are computed again on the first tick of each day of their time zone,
+    /// and when the offset of the system time zone changes
+    pub fn tick(&mut self, now: &DateTime<Local>) -> Vec<usize> {
+        if self.follow_offset(now) {
+            println!("System time zone offset changed to {}, computing alarm times again", now.offset().fix());
+        }
+        self.launch(now)
+    }
+
+    /// Keeps the offset of the system time zone, forgetting the computed alarm times when it changes
+    /// Returns whether it changed
+    fn follow_offset(&mut self, now: &DateTime<Local>) -> bool {
+        let utc_offset = now.offset().fix();
+        let changed = self.utc_offset.is_some_and(|previous| previous != utc_offset);
+        if changed {
+            for alarm in self.alarms.iter_mut() {
+                alarm.resolved.clear();
+            }
+        }
+        self.utc_offset = Some(utc_offset);
+        changed
+    }
+
+    /// Returns the positions of the enabled alarms due at the given time and marks them as run
+    /// Alarms with a count of runs disable themselves after their last run
+    fn launch(&mut self, now: &DateTime<Local>) -> Vec<usize> {
+        let mut started: Vec<usize> = vec![];
+
+        for (index, alarm) in self.alarms.iter_mut().enumerate().filter(|(_, alarm)| !alarm.disabled) {
+            let today = alarm.local_time(now).date();
+            alarm.refresh(today);
+
+            if alarm.is_due(now) {
+                alarm.last_run = *now;
+                alarm.count_run();
+                started.push(index);
+            }
+        }
+
+        started
+    }
+
+    /// Runs the alarms from `from` to `to` on a virtual clock, without playing them
+    /// Returns the positions of the alarms launched with their run time
+    pub fn simulate(&mut self, from: &DateTime<Local>, to: &DateTime<Local>) -> Vec<(DateTime<Local>, usize)> {
+        for alarm in self.alarms.iter_mut() {
+            alarm.last_run = *from;
+            alarm.resolved.clear();
+        }
+        self.utc_offset = None;
+
+        let mut launched: Vec<(DateTime<Local>, usize)> = vec![];
+        let mut now = *from;
+        loop {
+            // the clock jumps to the next run, or a week ahead when no alarm runs in the coming week
+            now = self.next(&now).map_or(now + chrono::Duration::days(7), |(run, _)| run);
+            if now > *to {
+                break;
+            }
+            self.follow_offset(&now);
+            launched.extend(self.launch(&now).into_iter().map(|index| (now, index)));
+        }
+        launched
+    }
+
+    /// Runs of the alarms after `from`, up to `to` included, with the positions of the alarms, in time order
+    pub fn agenda(&self, from: &DateTime<Local>, to: &DateTime<Local>) -> Vec<(DateTime<Local>, usize)> {
+        let mut runs: Vec<(DateTime<Local>, usize)> = self.alarms.iter()
+            .enumerate()
+            .flat_map(|(index, alarm)| alarm.occurrences(from, to).into_iter().map(move |run| (run, index)))
+            .collect();
+        runs.sort();
+        runs
+    }
+
+    /// Finds an alarm by label, case insensitive, or by position starting at 1
+    pub fn find(&self, id: &str) -> Option<usize> {
+        find(&self.alarms, id)
+    }
+
+    /// Position of the next alarm to run after the given time, with its run time
+    pub fn next(&self, now: &DateTime<Local>) -> Option<(DateTime<Local>, usize)> {
+        self.alarms.iter()
+            .enumerate()
+            .filter_map(|(index, alarm)| alarm.next_run(now).map(|run| (run, index)))
+            .min_by_key(|&(run, _)| run)
+    }
+}
@@ -0,0 +1,75 @@
+use calendar;
+use chrono::prelude::*;
+use chrono::LocalResult;
+use config::{AlarmConfig, Schedule};
+use scheduler::Scheduler;
+
+/// Run of an alarm found by a simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub time: DateTime<Local>,
+    /// Position of the alarm in the scheduler
+    pub alarm: usize,
+    /// What made the alarm run at this time rather than at its configured time
+    pub notes: Vec<String>
+}
+
+/// Explains the time of a run: daylight saving time transitions and times computed each day
+fn notes(alarm: &AlarmConfig, run: &DateTime<Local>) -> Vec<String> {
+    let mut notes: Vec<String> = vec![];
+    let date = alarm.local_time(run).date();
+    // the time of the day of a reminder run, the last one planned before it
+    let seconds = alarm.local_time(run).num_seconds_from_midnight();
+    let time = alarm.times_on(date).into_iter()
+        .take_while(|time| time.to_seconds() <= seconds)
+        .last()
+        .unwrap_or_else(|| alarm.time_on(date));
+
+    match date.and_hms_opt(time.hours, time.minutes, time.seconds).map(|naive| alarm.timezone.from_local(&naive)) {
+        Some(LocalResult::None) => notes.push(format!("clocks go forward, {} is skipped and the alarm runs at the end of the gap", time)),
+        Some(LocalResult::Ambiguous(_, _)) => notes.push(format!("clocks go back, {} happens twice and the alarm runs the first time", time)),
+        _ => {}
+    }
+
+    match alarm.schedule {
+        Schedule::Fixed => {},
+        Schedule::Calendar(ref rule) => {
+            // the run of an event shortly after midnight happens the day before
+            let day = date.succ_opt()
+                .filter(|next| alarm.start_on(*next) == alarm.local_time(run))
+                .unwrap_or(date);
+            let event = calendar::read_events(&rule.path).ok()
+                .and_then(|events| calendar::first_event_on(&events, day, rule.category.as_deref(), alarm.timezone).map(|(event, _)| event.summary.clone()));
+            match event {
+                Some(summary) if day != date => notes.push(format!("before the first event of the calendar on the next day: {}", summary)),
+                Some(summary) => notes.push(format!("before the first event of the calendar: {}", summary)),
+                None => notes.push("no event in the calendar, configured time".to_string())
+            }
+        },
+        Schedule::Solar(ref rule) if rule.time_on(date, alarm.timezone).is_none() => notes.push(format!("no {} that day, configured time", rule.event.as_str())),
+        Schedule::Solar(ref rule) => notes.push(format!("computed from {}", rule.event.as_str())),
+        Schedule::Random(ref until) => notes.push(format!("drawn between {} and {}", alarm.time, until)),
+        Schedule::Interval(_) => {}
+    }
+    notes
+}
+
+/// Runs the alarms from `from` to `to` through the scheduler on a virtual clock, without playing them
+/// Notes explain the runs that do not happen at the configured time,
+/// and the runs after which the offset of the system time zone changed
+pub fn simulate(alarms: Vec<AlarmConfig>, from: &DateTime<Local>, to: &DateTime<Local>) -> (Scheduler, Vec<Event>) {
+    let mut scheduler = Scheduler::new(alarms);
+    let runs = scheduler.simulate(from, to);
+
+    let mut offset = from.offset().fix();
+    let events = runs.into_iter().map(|(time, index)| {
+        let mut notes = notes(&scheduler.alarms[index], &time);
+        if time.offset().fix() != offset {
+            offset = time.offset().fix();
+            notes.insert(0, format!("system time offset is now {}", offset));
+        }
+        Event { time, alarm: index, notes }
+    }).collect();
+
+    (scheduler, events)
+}
@@ -0,0 +1,84 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use std::f64::consts::PI;
+
+/// Sun events an alarm can be related to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolarEvent {
+    Sunrise,
+    Sunset
+}
+
+impl SolarEvent {
+    pub fn as_str(&self) -> &str {
+        match *self {
+            SolarEvent::Sunrise => "sunrise",
+            SolarEvent::Sunset => "sunset"
+        }
+    }
+}
+
+/// Official zenith angle for sunrise and sunset, refraction included
+const ZENITH: f64 = 90.833;
+
+fn sin_deg(d: f64) -> f64 { (d * PI / 180.0).sin() }
+fn cos_deg(d: f64) -> f64 { (d * PI / 180.0).cos() }
+fn tan_deg(d: f64) -> f64 { (d * PI / 180.0).tan() }
+fn asin_deg(x: f64) -> f64 { x.asin() * 180.0 / PI }
+fn acos_deg(x: f64) -> f64 { x.acos() * 180.0 / PI }
+fn atan_deg(x: f64) -> f64 { x.atan() * 180.0 / PI }
+
+/// Computes the time of a sun event for a UTC day, offline
+/// Uses the sunrise/sunset algorithm of the Almanac for Computers, accurate to a couple of minutes
+/// Returns None when the sun does not rise or set that day (polar day or night)
+pub fn event_time(date: NaiveDate, latitude: f64, longitude: f64, event: SolarEvent) -> Option<DateTime<Utc>> {
+    let day_of_year = date.ordinal() as f64;
+    let longitude_hour = longitude / 15.0;
+
+    let approximate = match event {
+        SolarEvent::Sunrise => day_of_year + (6.0 - longitude_hour) / 24.0,
+        SolarEvent::Sunset => day_of_year + (18.0 - longitude_hour) / 24.0
+    };
+
+    // sun mean anomaly and true longitude
+    let mean_anomaly = 0.9856 * approximate - 3.289;
+    let true_longitude = (mean_anomaly + 1.916 * sin_deg(mean_anomaly) + 0.020 * sin_deg(2.0 * mean_anomaly) + 282.634).rem_euclid(360.0);
+
+    // right ascension, in the same quadrant as the true longitude, in hours
+    let right_ascension = atan_deg(0.91764 * tan_deg(true_longitude)).rem_euclid(360.0);
+    let right_ascension = (right_ascension + (true_longitude / 90.0).floor() * 90.0 - (right_ascension / 90.0).floor() * 90.0) / 15.0;
+
+    // declination and local hour angle
+    let sin_declination = 0.39782 * sin_deg(true_longitude);
+    let cos_declination = cos_deg(asin_deg(sin_declination));
+    let cos_hour_angle = (cos_deg(ZENITH) - sin_declination * sin_deg(latitude)) / (cos_declination * cos_deg(latitude));
+
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+
+    let hour_angle = match event {
+        SolarEvent::Sunrise => 360.0 - acos_deg(cos_hour_angle),
+        SolarEvent::Sunset => acos_deg(cos_hour_angle)
+    } / 15.0;
+
+    let local_mean_time = hour_angle + right_ascension - 0.06571 * approximate - 6.622;
+    let universal_time = (local_mean_time - longitude_hour).rem_euclid(24.0);
+
+    let midnight = Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?);
+    Some(midnight + Duration::seconds((universal_time * 3600.0).round() as i64))
+}
+
+#[test]
+fn test_event_time() {
+    // Paris, summer solstice: sunrise 03:47 UTC, sunset 19:58 UTC
+    let date = NaiveDate::from_ymd_opt(2026, 6, 21).unwrap();
+    let sunrise = event_time(date, 48.8566, 2.3522, SolarEvent::Sunrise).unwrap();
+    let sunset = event_time(date, 48.8566, 2.3522, SolarEvent::Sunset).unwrap();
+    assert!((sunrise - Utc.with_ymd_and_hms(2026, 6, 21, 3, 47, 0).unwrap()).num_minutes().abs() <= 3);
+    assert!((sunset - Utc.with_ymd_and_hms(2026, 6, 21, 19, 58, 0).unwrap()).num_minutes().abs() <= 3);
+
+    // Tromsø, polar night
+    let winter = NaiveDate::from_ymd_opt(2026, 12, 21).unwrap();
+    assert_eq!(event_time(winter, 69.6492, 18.9553, SolarEvent::Sunrise), None);
+}
@@ -0,0 +1,168 @@
+use chrono::prelude::*;
+use config::{AlarmConfig, Exception};
+use std::fs;
+use std::io;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// State of the alarms kept between runs, written in TOML next to the configuration file
+/// Alarms are referred to by their key, see `AlarmConfig::key`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct State {
+    /// Skipped or moved next runs, by alarm key
+    pub exceptions: Vec<(String, Exception)>,
+    /// Runs of the alarms with a count of runs, by alarm key
+    pub runs: Vec<(String, u32)>
+}
+
+/// Reads an instant written in RFC 3339
+fn parse_instant(table: &Table, key: &str) -> Result<Option<DateTime<Local>>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => DateTime::parse_from_rfc3339(s)
+            .map(|instant| Some(instant.with_timezone(&Local)))
+            .map_err(|err| format!("Invalid {}: {}: {}", key, s, err)),
+        Some(other) => Err(format!("Field {} must be a string, not a {}", key, other.type_str()))
+    }
+}
+
+impl State {
+
+    /// Parses the content of a state file
+    pub fn parse(s: &str) -> Result<State, String> {
+        let table: Table = s.parse().map_err(|err: toml::de::Error| err.message().to_string())?;
+        let mut state = State::default();
+
+        if let Some(exceptions) = table.get("exception") {
+            let exceptions = exceptions.as_array().ok_or("exception must be an array of tables")?;
+            for exception in exceptions.iter() {
+                let exception = exception.as_table().ok_or("exception must be an array of tables")?;
+                let alarm = exception.get("alarm").and_then(|alarm| alarm.as_str()).ok_or("Missing alarm in exception")?;
+                let run = parse_instant(exception, "run")?.ok_or("Missing run in exception")?;
+                let moved_to = parse_instant(exception, "moved_to")?;
+                state.exceptions.push((alarm.to_string(), Exception { run, moved_to }));
+            }
+        }
+
+        if let Some(counters) = table.get("counter") {
+            let counters = counters.as_array().ok_or("counter must be an array of tables")?;
+            for counter in counters.iter() {
+                let counter = counter.as_table().ok_or("counter must be an array of tables")?;
+                let alarm = counter.get("alarm").and_then(|alarm| alarm.as_str()).ok_or("Missing alarm in counter")?;
+                let runs = counter.get("runs").and_then(|runs| runs.as_integer())
+                    .filter(|runs| *runs >= 0)
+                    .ok_or("Missing number of runs in counter")?;
+                state.runs.push((alarm.to_string(), runs as u32));
+            }
+        }
+        Ok(state)
+    }
+
+    /// Content of the state file
+    pub fn to_toml(&self) -> String {
+        let exceptions = self.exceptions.iter().map(|(alarm, exception)| {
+            let mut table = Table::new();
+            table.insert("alarm".to_string(), Value::String(alarm.clone()));
+            table.insert("run".to_string(), Value::String(exception.run.to_rfc3339()));
+            if let Some(ref moved_to) = exception.moved_to {
+                table.insert("moved_to".to_string(), Value::String(moved_to.to_rfc3339()));
+            }
+            Value::Table(table)
+        }).collect::<Vec<_>>();
+
+        let counters = self.runs.iter().map(|(alarm, runs)| {
+            let mut table = Table::new();
+            table.insert("alarm".to_string(), Value::String(alarm.clone()));
+            table.insert("runs".to_string(), Value::Integer(*runs as i64));
+            Value::Table(table)
+        }).collect::<Vec<_>>();
+
+        let mut table = Table::new();
+        if !exceptions.is_empty() {
+            table.insert("exception".to_string(), Value::Array(exceptions));
+        }
+        if !counters.is_empty() {
+            table.insert("counter".to_string(), Value::Array(counters));
+        }
+        table.to_string()
+    }
+
+    /// Reads the state file, an empty state when it does not exist or is invalid
+    pub fn load(path: &Path) -> State {
+        match fs::read_to_string(path) {
+            Ok(content) => State::parse(&content).unwrap_or_else(|err| {
+                println!("Ignoring invalid state file {}: {}", path.display(), err);
+                State::default()
+            }),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => State::default(),
+            Err(err) => {
+                println!("Unable to read state file {}: {}", path.display(), err);
+                State::default()
+            }
+        }
+    }
+
+    /// Writes the state file
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_toml()).map_err(|err| format!("Unable to write {}: {}", path.display(), err))
+    }
+
+    /// Gives the alarms their exceptions and their counted runs
+    pub fn apply(&self, alarms: &mut [AlarmConfig]) {
+        for alarm in alarms.iter_mut() {
+            let key = alarm.key();
+            alarm.exception = self.exceptions.iter()
+                .find(|(alarm, _)| *alarm == key)
+                .map(|(_, exception)| exception.clone());
+            alarm.runs = self.runs.iter()
+                .find(|(alarm, _)| *alarm == key)
+                .map_or(0, |&(_, runs)| runs);
+        }
+    }
+
+    /// Sets the counted runs of an alarm, 0 removes its counter
+    pub fn set_runs(&mut self, key: &str, runs: u32) {
+        self.runs.retain(|(alarm, _)| alarm != key);
+        if runs > 0 {
+            self.runs.push((key.to_string(), runs));
+        }
+    }
+
+    /// Sets or removes the exception of an alarm
+    pub fn set_exception(&mut self, key: &str, exception: Option<Exception>) {
+        self.exceptions.retain(|(alarm, _)| alarm != key);
+        if let Some(exception) = exception {
+            self.exceptions.push((key.to_string(), exception));
+        }
+    }
+
+    /// Drops the exceptions that have passed, from the state and from the alarms
+    /// Returns true when the state changed
+    pub fn expire(&mut self, alarms: &mut [AlarmConfig], now: &DateTime<Local>) -> bool {
+        for alarm in alarms.iter_mut() {
+            alarm.expire_exception(now);
+        }
+        let count = self.exceptions.len();
+        self.exceptions.retain(|(_, exception)| *now <= exception.moved_to.map_or(exception.run, |moved_to| moved_to.max(exception.run)));
+        self.exceptions.len() != count
+    }
+}
+
+#[test]
+fn test_state_file() {
+    let run = Local.with_ymd_and_hms(2026, 10, 19, 6, 30, 0).unwrap();
+    let moved_to = Local.with_ymd_and_hms(2026, 10, 19, 5, 45, 0).unwrap();
+    let state = State {
+        exceptions: vec![
+            ("Wake up".to_string(), Exception { run, moved_to: Some(moved_to) }),
+            ("config:3".to_string(), Exception { run, moved_to: None })
+        ],
+        runs: vec![("Vitamins".to_string(), 2)]
+    };
+
+    assert_eq!(State::parse(&state.to_toml()), Ok(state));
+    assert_eq!(State::parse(""), Ok(State::default()));
+    assert!(State::parse("[[exception]]\nalarm = \"Wake up\"\nrun = \"tomorrow\"").is_err());
+    assert!(State::parse("[[exception]]\nrun = \"2026-10-19T06:30:00+02:00\"").is_err());
+    assert!(State::parse("[[counter]]\nalarm = \"Vitamins\"\nruns = -1").is_err());
+}
@@ -0,0 +1,116 @@
+use calendar::{first_event_on, parse_events, EventStart};
+use chrono::prelude::*;
+use zone::Zone;
+
+const ICS: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+DTSTART:20261020T100000\r
+SUMMARY:Standup\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Europe/Paris:20261020T083000\r
+SUMMARY:On-call\r
+  handover\r
+CATEGORIES:Work,On-call\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20261020\r
+SUMMARY:Holiday\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+#[test]
+fn parse_events_reads_timed_events() {
+    let events = parse_events(ICS).0;
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].summary, "On-call handover");
+    assert_eq!(events[1].categories, vec!["Work".to_string(), "On-call".to_string()]);
+    assert_eq!(events[1].start, EventStart::Fixed(Utc.with_ymd_and_hms(2026, 10, 20, 6, 30, 0).unwrap()));
+}
+
+#[test]
+fn first_event_on_filters_day_and_category() {
+    let events = parse_events(ICS).0;
+    let day = NaiveDate::from_ymd_opt(2026, 10, 20).unwrap();
+    let paris = Zone::Named(chrono_tz::Europe::Paris);
+
+    assert_eq!(first_event_on(&events, day, None, paris).unwrap().0.summary, "On-call handover");
+    assert_eq!(first_event_on(&events, day, Some("ON-CALL"), paris).unwrap().0.summary, "On-call handover");
+    assert!(first_event_on(&events, day, Some("Personal"), paris).is_none());
+    assert!(first_event_on(&events, day.succ_opt().unwrap(), None, paris).is_none());
+}
+
+#[test]
+fn first_event_on_reads_events_in_the_alarm_time_zone() {
+    let events = parse_events(ICS).0;
+    let day = NaiveDate::from_ymd_opt(2026, 10, 20).unwrap();
+    let tokyo = Zone::Named(chrono_tz::Asia::Tokyo);
+
+    // the 08:30 Paris event starts at 15:30 in Tokyo, after the floating 10:00 standup
+    let (event, start) = first_event_on(&events, day, None, tokyo).unwrap();
+    assert_eq!(event.summary, "Standup");
+    assert_eq!(start, day.and_hms_opt(10, 0, 0).unwrap());
+}
+
+const RECURRING_ICS: &str = "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:on-call
+DTSTART;TZID=Europe/Paris:20261019T070000
+RRULE:FREQ=WEEKLY;BYDAY=MO,TH;COUNT=6
+EXDATE;TZID=Europe/Paris:20261022T070000
+SUMMARY:On-call
+END:VEVENT
+BEGIN:VEVENT
+UID:on-call
+RECURRENCE-ID;TZID=Europe/Paris:20261026T070000
+DTSTART;TZID=Europe/Paris:20261026T090000
+SUMMARY:On-call, late start
+END:VEVENT
+END:VCALENDAR
+";
+
+#[test]
+fn first_event_on_expands_recurring_events() {
+    let events = parse_events(RECURRING_ICS).0;
+    let paris = Zone::Named(chrono_tz::Europe::Paris);
+    let day = |d: u32| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+    let first = |date: NaiveDate| first_event_on(&events, date, None, paris).map(|(event, start)| (event.summary.clone(), start.time()));
+    let seven = NaiveTime::from_hms_opt(7, 0, 0).unwrap();
+
+    assert_eq!(first(day(19)), Some(("On-call".to_string(), seven)));
+    // removed by EXDATE
+    assert_eq!(first(day(22)), None);
+    assert_eq!(first(day(21)), None);
+    // replaced by the event with a RECURRENCE-ID, after the change to winter time
+    assert_eq!(first(day(26)), Some(("On-call, late start".to_string(), NaiveTime::from_hms_opt(9, 0, 0).unwrap())));
+    assert_eq!(first(day(29)), Some(("On-call".to_string(), seven)));
+    // the sixth and last occurrence
+    assert_eq!(first(NaiveDate::from_ymd_opt(2026, 11, 5).unwrap()), Some(("On-call".to_string(), seven)));
+    assert_eq!(first(NaiveDate::from_ymd_opt(2026, 11, 9).unwrap()), None);
+}
+
+#[test]
+fn parse_events_skips_events_with_unsupported_recurrences() {
+    let ics = "BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:review
+DTSTART:20261019T070000
+RRULE:FREQ=MONTHLY;BYDAY=1MO
+SUMMARY:Review
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20261020T090000
+SUMMARY:Standup
+END:VEVENT
+END:VCALENDAR
+";
+    let (events, warnings) = parse_events(ics);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].summary, "Standup");
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].starts_with("Skipped event review at line 2: "), "{}", warnings[0]);
+}
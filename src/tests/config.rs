@@ -1,7 +1,13 @@
 use chrono::{DateTime, Local};
-use config::{AlarmConfig, Time};
+use check::check;
+use config::{load_configuration, same_configuration, AlarmConfig, CalendarRule, Diagnostic, Origin, Schedule, Time};
+use config::edit;
 use chrono::prelude::*;
 use tests::oldtime::Duration;
+use scheduler::Scheduler;
+use zone::Zone;
+use std::path::PathBuf;
+use tests::TempDir;
 
 #[test]
 fn already_run_today_check_true() {
@@ -20,7 +26,8 @@ fn already_run_today_check_true() {
         time: time_for_alarm,
         audio_file: "/fake/path".to_string(),
         volume: 100,
-        last_run: before
+        last_run: before,
+        ..AlarmConfig::default()
     };
 
     assert_eq!(alarm_config.already_run_today(&now), true);
@@ -43,7 +50,8 @@ fn already_run_today_check_false() {
         time: time_for_alarm,
         audio_file: "/fake/path".to_string(),
         volume: 100,
-        last_run: now
+        last_run: now,
+        ..AlarmConfig::default()
     };
 
     assert_eq!(alarm_config.already_run_today(&now), false);
@@ -68,7 +76,8 @@ fn is_expired_check_true() {
         time: time_for_alarm,
         audio_file: "/fake/path".to_string(),
         volume: 100,
-        last_run: before
+        last_run: before,
+        ..AlarmConfig::default()
     };
 
     assert_eq!(alarm_config.is_expired(&now), true);
@@ -93,9 +102,364 @@ fn is_expired_check_false() {
         time: time_for_alarm,
         audio_file: "/fake/path".to_string(),
         volume: 100,
-        last_run: after
+        last_run: after,
+        ..AlarmConfig::default()
     };
 
     assert_eq!(alarm_config.is_expired(&now), false);
 }
 
+
+#[test]
+fn calendar_alarm_runs_before_first_event() {
+
+    let dir = TempDir::new("calendar_alarm");
+    let path = dir.write("calendar.ics", "BEGIN:VCALENDAR
+BEGIN:VEVENT
+DTSTART:20261020T100000
+SUMMARY:Standup
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20261020T083000
+SUMMARY:On-call handover
+CATEGORIES:On-call
+END:VEVENT
+END:VCALENDAR
+");
+
+    let rule = CalendarRule {
+        path: path.to_str().unwrap().to_string(),
+        category: None,
+        before: 3600
+    };
+
+    let mut alarm_config = AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 7, minutes: 0, seconds: 0 },
+        schedule: Schedule::Calendar(rule.clone()),
+        ..AlarmConfig::default()
+    };
+
+    let event_day = NaiveDate::from_ymd_opt(2026, 10, 20).unwrap();
+    let other_day = NaiveDate::from_ymd_opt(2026, 10, 21).unwrap();
+
+    // one hour before the 08:30 event, the configured time otherwise
+    assert_eq!(alarm_config.time_on(event_day), Time { hours: 7, minutes: 30, seconds: 0 });
+    assert_eq!(alarm_config.time_on(other_day), Time { hours: 7, minutes: 0, seconds: 0 });
+
+    alarm_config.refresh(event_day);
+    // the next day is kept along, for the alarms running just after midnight
+    assert_eq!(alarm_config.resolved, vec![(event_day, event_day.and_hms_opt(7, 30, 0).unwrap()), (other_day, other_day.and_hms_opt(7, 0, 0).unwrap())]);
+
+    alarm_config.schedule = Schedule::Calendar(CalendarRule {
+        category: Some("Personal".to_string()),
+        ..rule
+    });
+    alarm_config.resolved.clear();
+    assert_eq!(alarm_config.time_on(event_day), Time { hours: 7, minutes: 0, seconds: 0 });
+}
+
+#[test]
+fn calendar_alarm_moves_to_the_day_before_an_early_event() {
+
+    let dir = TempDir::new("calendar_early");
+    let path = dir.write("calendar.ics", "BEGIN:VCALENDAR
+BEGIN:VEVENT
+DTSTART:20261021T003000
+SUMMARY:Night shift
+END:VEVENT
+END:VCALENDAR
+");
+
+    let alarm_config = AlarmConfig {
+        days: vec![false, false, true, false, false, false, false], // wednesday
+        time: Time { hours: 7, minutes: 0, seconds: 0 },
+        schedule: Schedule::Calendar(CalendarRule { path: path.to_str().unwrap().to_string(), category: None, before: 3600 }),
+        last_run: Local.with_ymd_and_hms(2026, 10, 20, 12, 0, 0).unwrap(),
+        ..AlarmConfig::default()
+    };
+
+    let event_day = NaiveDate::from_ymd_opt(2026, 10, 21).unwrap();
+
+    // one hour before the 00:30 event is 23:30 on the tuesday, an inactive day
+    assert_eq!(alarm_config.start_on(event_day), NaiveDate::from_ymd_opt(2026, 10, 20).unwrap().and_hms_opt(23, 30, 0).unwrap());
+    assert!(!alarm_config.is_due(&Local.with_ymd_and_hms(2026, 10, 20, 23, 29, 59).unwrap()));
+    assert!(alarm_config.is_due(&Local.with_ymd_and_hms(2026, 10, 20, 23, 30, 0).unwrap()));
+}
+
+#[test]
+fn next_run_finds_the_next_active_day() {
+
+    let now: DateTime<Local> = Local.with_ymd_and_hms(2026, 10, 20, 5, 0, 0).unwrap(); // tuesday
+
+    let alarm_config = AlarmConfig {
+        days: vec![false, true, false, true, false, false, false],
+        time: Time { hours: 6, minutes: 30, seconds: 0 },
+        last_run: now - Duration::days(1),
+        ..AlarmConfig::default()
+    };
+
+    assert_eq!(alarm_config.next_run(&now), Some(Local.with_ymd_and_hms(2026, 10, 20, 6, 30, 0).unwrap()));
+
+    let later = Local.with_ymd_and_hms(2026, 10, 20, 7, 0, 0).unwrap();
+    assert_eq!(alarm_config.next_run(&later), Some(Local.with_ymd_and_hms(2026, 10, 22, 6, 30, 0).unwrap()));
+}
+
+#[test]
+fn already_run_today_compares_times_of_the_day() {
+    let at = |hours, minutes, seconds| Local.with_ymd_and_hms(2026, 10, 20, hours, minutes, seconds).unwrap();
+    let run_at = |last_run: DateTime<Local>, hours, minutes, seconds| AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours, minutes, seconds },
+        last_run,
+        ..AlarmConfig::default()
+    };
+
+    // the comparisons of the former hms_gte helper, between the last run and the time of the alarm
+    assert!(run_at(at(0, 0, 0), 0, 0, 0).already_run_today(&at(12, 0, 0)));
+    assert!(run_at(at(8, 0, 0), 7, 59, 59).already_run_today(&at(12, 0, 0)));
+    assert!(run_at(at(23, 0, 0), 0, 59, 59).already_run_today(&at(23, 30, 0)));
+    assert!(run_at(at(0, 1, 0), 0, 0, 59).already_run_today(&at(12, 0, 0)));
+    assert!(!run_at(at(7, 59, 59), 8, 0, 0).already_run_today(&at(12, 0, 0)));
+    assert!(!run_at(at(0, 0, 59), 0, 1, 0).already_run_today(&at(12, 0, 0)));
+}
+
+#[test]
+fn key_of_unlabelled_alarms_does_not_depend_on_the_line() {
+
+    let alarm_config = AlarmConfig {
+        time: Time { hours: 6, minutes: 30, seconds: 0 },
+        origin: Origin { file: PathBuf::from("/etc/rustine/config"), line: Some(3) },
+        ..AlarmConfig::default()
+    };
+    let moved = AlarmConfig { origin: Origin { line: Some(7), ..alarm_config.origin.clone() }, disabled: true, ..alarm_config.clone() };
+    let changed = AlarmConfig { time: Time { hours: 7, minutes: 0, seconds: 0 }, ..alarm_config.clone() };
+    let other_file = AlarmConfig { origin: Origin { file: PathBuf::from("/etc/rustine/conf.d/kids.conf"), line: Some(3) }, ..alarm_config.clone() };
+
+    assert!(alarm_config.key().starts_with("/etc/rustine/config#"));
+    assert_eq!(moved.key(), alarm_config.key());
+    assert_ne!(changed.key(), alarm_config.key());
+    assert_ne!(other_file.key(), alarm_config.key());
+    assert_eq!(AlarmConfig { label: Some("Wake".to_string()), ..alarm_config }.key(), "Wake");
+}
+
+#[test]
+fn already_run_today_compares_full_dates() {
+
+    let paris = Zone::Named(chrono_tz::Europe::Paris);
+    let alarm_config = AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 7, minutes: 0, seconds: 0 },
+        timezone: paris,
+        // 17 October, 07:00 in Paris
+        last_run: Utc.with_ymd_and_hms(2026, 10, 17, 5, 0, 0).unwrap().with_timezone(&Local),
+        ..AlarmConfig::default()
+    };
+
+    // 17 November, 07:00:01 in Paris
+    let now = Utc.with_ymd_and_hms(2026, 11, 17, 6, 0, 1).unwrap().with_timezone(&Local);
+    assert!(!alarm_config.already_run_today(&now));
+    assert!(alarm_config.is_expired(&now));
+}
+
+#[test]
+fn nonexistent_time_runs_at_the_end_of_the_gap() {
+
+    // clocks go from 02:00 to 03:00 on 29 March 2026 in Paris
+    let alarm_config = AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 2, minutes: 30, seconds: 0 },
+        timezone: Zone::Named(chrono_tz::Europe::Paris),
+        last_run: Utc.with_ymd_and_hms(2026, 3, 28, 1, 30, 0).unwrap().with_timezone(&Local),
+        ..AlarmConfig::default()
+    };
+
+    let before_gap = Utc.with_ymd_and_hms(2026, 3, 29, 0, 59, 59).unwrap().with_timezone(&Local);
+    let after_gap = Utc.with_ymd_and_hms(2026, 3, 29, 1, 0, 0).unwrap().with_timezone(&Local);
+
+    assert!(!alarm_config.is_expired(&before_gap));
+    assert!(alarm_config.is_expired(&after_gap));
+    assert_eq!(alarm_config.next_run(&before_gap), Some(after_gap));
+}
+
+#[test]
+fn ambiguous_time_runs_once() {
+
+    // clocks go from 03:00 back to 02:00 on 25 October 2026 in Paris, 02:30 happens twice
+    let mut scheduler = Scheduler::new(vec![AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 2, minutes: 30, seconds: 0 },
+        timezone: Zone::Named(chrono_tz::Europe::Paris),
+        last_run: Utc.with_ymd_and_hms(2026, 10, 24, 0, 30, 0).unwrap().with_timezone(&Local),
+        ..AlarmConfig::default()
+    }]);
+
+    let first = Utc.with_ymd_and_hms(2026, 10, 25, 0, 30, 0).unwrap().with_timezone(&Local);
+    let second = Utc.with_ymd_and_hms(2026, 10, 25, 1, 30, 0).unwrap().with_timezone(&Local);
+
+    assert!(scheduler.tick(&(first - Duration::seconds(1))).is_empty());
+    assert_eq!(scheduler.tick(&first).len(), 1);
+    assert!(scheduler.tick(&second).is_empty());
+}
+
+#[test]
+fn random_time_is_drawn_once_per_day_within_the_window() {
+
+    let alarm_config = AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 7, minutes: 0, seconds: 0 },
+        schedule: Schedule::Random(Time { hours: 7, minutes: 10, seconds: 0 }),
+        audio_file: "/fake/path".to_string(),
+        ..AlarmConfig::default()
+    };
+
+    let first_day = NaiveDate::from_ymd_opt(2026, 10, 20).unwrap();
+    let times: Vec<Time> = (0..30)
+        .map(|offset| alarm_config.time_on(first_day + Duration::days(offset)))
+        .collect();
+
+    for time in times.iter() {
+        assert!(time.to_seconds() >= 7 * 3600 && time.to_seconds() <= 7 * 3600 + 600);
+    }
+    // stable for a given day
+    assert_eq!(alarm_config.time_on(first_day), times[0]);
+    // drawn again on other days
+    assert!(times.iter().any(|time| *time != times[0]));
+}
+
+#[test]
+fn banner_frames_label_and_message() {
+
+    let alarm_config = AlarmConfig {
+        label: Some("Wake up".to_string()),
+        message: Some("Stand-up at 9".to_string()),
+        ..AlarmConfig::default()
+    };
+
+    assert_eq!(alarm_config.banner(), Some("*****************
+* Wake up       *
+* Stand-up at 9 *
+*****************".to_string()));
+    assert_eq!(AlarmConfig::default().banner(), None);
+}
+
+#[test]
+fn configuration_reads_includes_and_conf_d() {
+
+    let dir = TempDir::new("includes");
+    dir.write("config", "audio=/default.ogg
+MTWTF__;06:30:00;;;label=Main
+include shared/extra
+include missing
+_____SS;09:00:00;;;label=Weekend
+");
+    dir.write("shared/extra", "MTWTFSS;12:00:00;;;label=Extra
+include ../config
+");
+    dir.write("conf.d/20-work.conf", "MTWTF__;08:00:00;sounds/work.ogg;;label=Work");
+    dir.write("conf.d/10-kids.conf", "MTWTF__;07:00:00;;;label=Kids\nMTWTF__;noon;;");
+    dir.write("conf.d/notes.txt", "not an alarm");
+
+    let (alarms, diagnostics) = load_configuration(dir.join("config").to_str().unwrap());
+
+    let labels: Vec<&str> = alarms.iter().map(|alarm| alarm.label.as_deref().unwrap()).collect();
+    assert_eq!(labels, vec!["Main", "Extra", "Weekend", "Kids", "Work"]);
+    // settings of the configuration file apply to included and conf.d files
+    assert_eq!(alarms[3].audio_file, "/default.ogg");
+    // relative paths are relative to the file defining the alarm
+    let conf_d = dir.join("conf.d").canonicalize().unwrap();
+    assert_eq!(alarms[4].audio_file, conf_d.join("sounds/work.ogg").to_str().unwrap());
+    assert_eq!(alarms[3].origin.file, dir.join("conf.d/10-kids.conf"));
+    assert_eq!(alarms[3].origin.line, Some(1));
+
+    let messages: Vec<String> = diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect();
+    assert_eq!(messages.len(), 3);
+    assert!(messages[0].starts_with(&format!("{}: error: Include cycle", dir.join("shared/../config").display())));
+    assert!(messages[1].starts_with(&format!("{}: error: Unable to read file", dir.join("missing").display())));
+    assert_eq!(messages[2], format!("{}:2: error: Invalid configuration: Invalid time: noon", dir.join("conf.d/10-kids.conf").display()));
+}
+
+#[test]
+fn reminders_play_the_chime_of_the_main_directory() {
+
+    let dir = TempDir::new("reminder_audio");
+    dir.write("config", "MTWTF__;06:30:00;/a.ogg;75\n");
+    dir.write("conf.d/breaks.conf", "MTWTF__;09:00..18:00;;;every=45m;label=Stretch\n");
+
+    // the chime is chosen when the reminder runs, reading the configuration does not look for it
+    let (alarms, diagnostics) = load_configuration(dir.join("config").to_str().unwrap());
+    assert_eq!(alarms[1].audio_file, "");
+    assert!(diagnostics.is_empty());
+
+    // the chime was not created, the reminder is kept and reported
+    let diagnostics: Vec<Diagnostic> = check(dir.join("config").to_str().unwrap()).1.into_iter()
+        .filter(|diagnostic| diagnostic.origin.file != dir.join("config"))
+        .collect();
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].to_string().starts_with(&format!("{}:1: warning: The reminder only shows its message", dir.join("conf.d/breaks.conf").display())));
+}
+
+#[test]
+fn inherited_paths_are_relative_to_the_file_setting_them() {
+
+    let dir = TempDir::new("inherited_paths");
+    dir.write("config", "audio=sounds/default.ogg\n");
+    dir.write("conf.d/kids.conf", "template kids;MTWTF__;;sounds/kids.ogg
+MTWTF__;07:00:00;;
+;07:30:00;;;template=kids
+");
+
+    let (alarms, diagnostics) = load_configuration(dir.join("config").to_str().unwrap());
+    assert!(diagnostics.is_empty());
+
+    let canonical = dir.path.canonicalize().unwrap();
+    assert_eq!(alarms[0].audio_file, canonical.join("sounds/default.ogg").to_str().unwrap());
+    assert_eq!(alarms[1].audio_file, canonical.join("conf.d/sounds/kids.ogg").to_str().unwrap());
+}
+
+#[test]
+fn configuration_skips_the_byte_order_mark() {
+
+    let dir = TempDir::new("bom_config");
+    let path = dir.write("config", "\u{feff}MTWTF__;06:30:00;/a.ogg;75\r\n");
+
+    let (alarms, diagnostics) = load_configuration(path.to_str().unwrap());
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(alarms[0].days, vec![true, true, true, true, true, false, false]);
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn edits_keep_comments_and_undo_invalid_changes() {
+
+    let dir = TempDir::new("edits");
+    let config = dir.write("config", "# house alarms
+template work;MTWTF__;;/work.ogg
+;06:30;;75;template=work;label=Wake   # weekdays
+");
+    dir.write("conf.d/kids.conf", "# kids\n;07:00;;;template=work;label=Kids\n");
+
+    let (alarms, position) = edit::add_alarm(&config, "weekends;9h;/lazy.ogg;50;label=Lazy").unwrap();
+    assert_eq!(position, 1);
+    assert_eq!(alarms[position].origin.line, Some(4));
+
+    // templates of the configuration file apply to the conf.d files
+    let alarms = edit::edit_alarm(&config, &alarms[2], "time=7:15;volume=40").unwrap();
+    assert_eq!(alarms[2].time, Time { hours: 7, minutes: 15, seconds: 0 });
+    assert_eq!(alarms[2].volume, 40);
+    assert_eq!(::std::fs::read_to_string(dir.join("conf.d/kids.conf")).unwrap(), "# kids\n;7:15;;40;template=work;label=Kids\n");
+
+    let alarms = edit::edit_alarm(&config, &alarms[0], "label=Wake up").unwrap();
+    assert!(edit::edit_alarm(&config, &alarms[0], "time=noon").is_err());
+    assert!(edit::add_alarm(&config, "weekends;9h;;50").is_err());
+    let alarms = edit::remove_alarm(&config, &alarms[1]).unwrap();
+    assert_eq!(::std::fs::read_to_string(&config).unwrap(), "# house alarms
+template work;MTWTF__;;/work.ogg
+;06:30;;75;template=work;label=Wake up # weekdays
+");
+
+    let (reloaded, diagnostics) = load_configuration(config.to_str().unwrap());
+    assert!(diagnostics.is_empty());
+    assert!(same_configuration(&alarms, &reloaded));
+    assert!(!same_configuration(&alarms, &reloaded[1..]));
+}
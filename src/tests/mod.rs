@@ -1,3 +1,51 @@
-extern crate time as oldtime;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
 
+/// Durations of the tests written with the time crate, which chrono provides since it stopped using it
+mod oldtime {
+    pub use chrono::Duration;
+}
+
+mod calendar;
 mod config;
+mod scheduler;
+mod simulate;
+
+/// Directory of a test in the temporary directory, removed with its files when the test ends, even when it fails
+pub struct TempDir {
+    pub path: PathBuf
+}
+
+impl TempDir {
+
+    /// Empty directory named after the process and the test, so that tests running at the same time do not share files
+    pub fn new(name: &str) -> TempDir {
+        let path = env::temp_dir().join(format!("rustine_{}_{}", process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    /// Path of a file of the directory
+    pub fn join<P: AsRef<Path>>(&self, file: P) -> PathBuf {
+        self.path.join(file)
+    }
+
+    /// Writes a file of the directory, creating its parent directories, and returns its path
+    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(&self, file: P, content: C) -> PathBuf {
+        let path = self.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,277 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use config::{load_configuration, same_configuration, AlarmConfig, IntervalRule, Schedule, Time};
+use notify::DebouncedEvent;
+use paths;
+use scheduler::Scheduler;
+use state::State;
+use tests::TempDir;
+use zone::Zone;
+
+#[test]
+fn tick_starts_alarms_in_their_time_zone() {
+
+    // 23:30 UTC on tuesday is 08:30 on wednesday in Tokyo and 01:30 on wednesday in Paris
+    let now: DateTime<Local> = Utc.with_ymd_and_hms(2026, 10, 20, 23, 30, 0).unwrap().with_timezone(&Local);
+
+    let tokyo = AlarmConfig {
+        days: vec![false, false, true, false, false, false, false],
+        time: Time { hours: 8, minutes: 0, seconds: 0 },
+        timezone: Zone::Named(chrono_tz::Asia::Tokyo),
+        last_run: now - Duration::days(1),
+        ..AlarmConfig::default()
+    };
+    let paris = AlarmConfig {
+        timezone: Zone::Named(chrono_tz::Europe::Paris),
+        ..tokyo.clone()
+    };
+
+    let mut scheduler = Scheduler::new(vec![tokyo, paris]);
+
+    let started = scheduler.tick(&now);
+    assert_eq!(started.len(), 1);
+    assert_eq!(scheduler.alarms[started[0]].timezone, Zone::Named(chrono_tz::Asia::Tokyo));
+
+    // already run
+    assert!(scheduler.tick(&(now + Duration::seconds(1))).is_empty());
+
+    let paris_run = scheduler.alarms[1].next_run(&now).unwrap();
+    assert_eq!(paris_run, Utc.with_ymd_and_hms(2026, 10, 21, 6, 0, 0).unwrap().with_timezone(&Local));
+}
+
+#[test]
+fn find_by_label_or_position() {
+
+    let alarm = AlarmConfig {
+        days: vec![true; 7],
+        ..AlarmConfig::default()
+    };
+    let scheduler = Scheduler::new(vec![
+        alarm.clone(),
+        AlarmConfig { label: Some("Wake up".to_string()), ..alarm.clone() },
+        AlarmConfig { label: Some("Gym".to_string()), ..alarm }
+    ]);
+
+    assert_eq!(scheduler.find("wake UP"), Some(1));
+    assert_eq!(scheduler.find("Gym"), Some(2));
+    assert_eq!(scheduler.find("1"), Some(0));
+    assert_eq!(scheduler.find("3"), Some(2));
+    assert_eq!(scheduler.find("0"), None);
+    assert_eq!(scheduler.find("4"), None);
+    assert_eq!(scheduler.find("Nap"), None);
+}
+
+#[test]
+fn simulate_and_agenda_run_enabled_alarms() {
+
+    // from monday 19 to monday 26 october 2026, clocks go back in Paris on sunday 25
+    let from: DateTime<Local> = Utc.with_ymd_and_hms(2026, 10, 19, 0, 0, 0).unwrap().with_timezone(&Local);
+    let to = from + Duration::days(7);
+
+    let weekdays = AlarmConfig {
+        days: vec![true, true, true, true, true, false, false],
+        time: Time { hours: 7, minutes: 0, seconds: 0 },
+        timezone: Zone::Named(chrono_tz::Europe::Paris),
+        ..AlarmConfig::default()
+    };
+    let sunday = AlarmConfig {
+        days: vec![false, false, false, false, false, false, true],
+        ..weekdays.clone()
+    };
+    let disabled = AlarmConfig { disabled: true, ..weekdays.clone() };
+    let mut scheduler = Scheduler::new(vec![weekdays, sunday, disabled]);
+
+    let agenda = scheduler.agenda(&from, &to);
+    let launched = scheduler.simulate(&from, &to);
+    assert_eq!(launched, agenda);
+    assert_eq!(launched.len(), 6);
+    assert!(launched.iter().all(|&(_, index)| index != 2));
+
+    assert_eq!(launched[0].0, Utc.with_ymd_and_hms(2026, 10, 19, 5, 0, 0).unwrap().with_timezone(&Local));
+    // winter time on sunday
+    assert_eq!(launched[5], (Utc.with_ymd_and_hms(2026, 10, 25, 6, 0, 0).unwrap().with_timezone(&Local), 1));
+}
+
+#[test]
+fn skipped_and_moved_runs_ring_once() {
+
+    // 02:00 on monday 19 october 2026 in Paris, the alarms run at 06:30
+    let now: DateTime<Local> = Utc.with_ymd_and_hms(2026, 10, 19, 0, 0, 0).unwrap().with_timezone(&Local);
+    let paris = |hours: u32, minutes| Utc.with_ymd_and_hms(2026, 10, 19, hours - 2, minutes, 0).unwrap().with_timezone(&Local);
+
+    let alarm = AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 6, minutes: 30, seconds: 0 },
+        timezone: Zone::Named(chrono_tz::Europe::Paris),
+        last_run: now,
+        ..AlarmConfig::default()
+    };
+    let mut scheduler = Scheduler::new(vec![
+        AlarmConfig { label: Some("Skipped".to_string()), ..alarm.clone() },
+        AlarmConfig { label: Some("Moved".to_string()), ..alarm }
+    ]);
+
+    let skip = scheduler.alarms[0].change_next_run(&now, None).unwrap();
+    assert_eq!(skip.run, paris(6, 30));
+    let moved = scheduler.alarms[1].change_next_run(&now, Some("05:45")).unwrap();
+    assert_eq!(moved.moved_to, Some(paris(5, 45)));
+    assert!(scheduler.alarms[1].change_next_run(&now, Some("01:00")).is_err());
+
+    let mut state = State::default();
+    state.set_exception("Skipped", Some(skip));
+    state.set_exception("Moved", Some(moved));
+    state.apply(&mut scheduler.alarms);
+
+    assert_eq!(scheduler.alarms[0].next_run(&now), Some(paris(6, 30) + Duration::days(1)));
+    assert_eq!(scheduler.next(&now).map(|(run, _)| run), Some(paris(5, 45)));
+    assert_eq!(scheduler.agenda(&now, &(now + Duration::hours(12))), vec![(paris(5, 45), 1)]);
+
+    assert_eq!(scheduler.tick(&paris(5, 45)), vec![1]);
+    assert!(scheduler.tick(&paris(6, 30)).is_empty());
+
+    // the exceptions expire once the planned runs have passed, without ringing at the planned time
+    let later = paris(6, 31);
+    assert!(state.expire(&mut scheduler.alarms, &later));
+    assert!(state.exceptions.is_empty());
+    assert!(scheduler.alarms.iter().all(|alarm| alarm.exception.is_none()));
+    assert!(scheduler.tick(&later).is_empty());
+    assert_eq!(scheduler.next(&later).map(|(run, _)| run), Some(paris(6, 30) + Duration::days(1)));
+}
+
+#[test]
+fn paused_alarms_run_again_on_the_day() {
+
+    // monday 19 october 2026, paused until thursday 22
+    let now: DateTime<Local> = Utc.with_ymd_and_hms(2026, 10, 19, 0, 0, 0).unwrap().with_timezone(&Local);
+    let alarm = AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 7, minutes: 0, seconds: 0 },
+        timezone: Zone::Named(chrono_tz::Europe::Paris),
+        paused_until: NaiveDate::from_ymd_opt(2026, 10, 22),
+        last_run: now,
+        ..AlarmConfig::default()
+    };
+    let mut scheduler = Scheduler::new(vec![alarm]);
+
+    let first = Utc.with_ymd_and_hms(2026, 10, 22, 5, 0, 0).unwrap().with_timezone(&Local);
+    assert_eq!(scheduler.next(&now).map(|(run, _)| run), Some(first));
+    assert_eq!(scheduler.agenda(&now, &(now + Duration::days(4))), vec![(first, 0)]);
+    assert!(scheduler.tick(&Utc.with_ymd_and_hms(2026, 10, 19, 5, 0, 0).unwrap().with_timezone(&Local)).is_empty());
+    assert_eq!(scheduler.tick(&first), vec![0]);
+}
+
+#[test]
+fn alarms_run_within_their_days_and_count() {
+
+    // from monday 19 to monday 26 october 2026
+    let from: DateTime<Local> = Utc.with_ymd_and_hms(2026, 10, 19, 0, 0, 0).unwrap().with_timezone(&Local);
+    let to = from + Duration::days(7);
+    let at_seven = |day: u32| Utc.with_ymd_and_hms(2026, 10, day, 5, 0, 0).unwrap().with_timezone(&Local);
+
+    let term = AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 7, minutes: 0, seconds: 0 },
+        timezone: Zone::Named(chrono_tz::Europe::Paris),
+        first_day: NaiveDate::from_ymd_opt(2026, 10, 21),
+        last_day: NaiveDate::from_ymd_opt(2026, 10, 23),
+        ..AlarmConfig::default()
+    };
+    let counted = AlarmConfig {
+        first_day: None,
+        last_day: None,
+        count: Some(3),
+        runs: 1,
+        ..term.clone()
+    };
+    let mut scheduler = Scheduler::new(vec![term, counted]);
+
+    let agenda = scheduler.agenda(&from, &to);
+    assert_eq!(agenda, vec![(at_seven(19), 1), (at_seven(20), 1), (at_seven(21), 0), (at_seven(22), 0), (at_seven(23), 0)]);
+    assert_eq!(scheduler.simulate(&from, &to), agenda);
+
+    // the counted alarm disabled itself after its third run
+    assert!(scheduler.alarms[1].disabled);
+    assert_eq!(scheduler.alarms[1].runs, 3);
+    assert!(!scheduler.alarms[0].disabled);
+}
+
+#[test]
+fn reminders_run_every_interval_of_their_window() {
+
+    // monday 19 october 2026, in Paris
+    let at = |hours: u32, minutes: u32| Utc.with_ymd_and_hms(2026, 10, 19, hours - 2, minutes, 0).unwrap().with_timezone(&Local);
+
+    let reminder = AlarmConfig {
+        days: vec![true, true, true, true, true, false, false],
+        time: Time { hours: 9, minutes: 0, seconds: 0 },
+        timezone: Zone::Named(chrono_tz::Europe::Paris),
+        schedule: Schedule::Interval(IntervalRule { every: 2700, until: Time { hours: 11, minutes: 0, seconds: 0 } }),
+        ..AlarmConfig::default()
+    };
+    let mut scheduler = Scheduler::new(vec![reminder]);
+
+    let agenda = scheduler.agenda(&at(8, 0), &at(23, 0));
+    assert_eq!(agenda, vec![(at(9, 0), 0), (at(9, 45), 0), (at(10, 30), 0)]);
+    assert_eq!(scheduler.simulate(&at(8, 0), &at(23, 0)), agenda);
+
+    // a late start launches the reminder once, then waits for the next interval
+    scheduler.alarms[0].last_run = at(8, 0);
+    assert_eq!(scheduler.tick(&at(9, 50)), vec![0]);
+    assert!(scheduler.tick(&at(10, 0)).is_empty());
+    assert_eq!(scheduler.alarms[0].next_run(&at(10, 0)), Some(at(10, 30)));
+    assert!(!scheduler.alarms[0].already_run_today(&at(10, 0)));
+    assert_eq!(scheduler.tick(&at(10, 30)), vec![0]);
+    assert!(scheduler.alarms[0].already_run_today(&at(10, 31)));
+}
+
+#[test]
+fn reloading_keeps_the_last_run_of_unchanged_alarms() {
+
+    let dir = TempDir::new("reload_last_run");
+    let config = dir.write("config", "MTWTF__;06:30:00;/a.ogg;75\nMTWTF__;07:00:00;/b.ogg;75\n");
+    let mut scheduler = Scheduler::new(load_configuration(config.to_str().unwrap()).0);
+    let last_run = Local::now() - Duration::days(1);
+    for alarm in scheduler.alarms.iter_mut() {
+        alarm.last_run = last_run;
+    }
+
+    // a line inserted above moves the unlabelled alarms, the second one also changes
+    dir.write("config", "# house alarms\nMTWTF__;06:30:00;/a.ogg;75\nMTWTF__;07:15:00;/b.ogg;75\n");
+    let (reloaded, _) = load_configuration(config.to_str().unwrap());
+    assert_eq!(reloaded[0].origin.line, Some(2));
+    assert_eq!(reloaded[0].key(), scheduler.alarms[0].key());
+    // the changed unlabelled alarm gets another key, it is not found again
+    assert_eq!(scheduler.reconfigure(reloaded), vec![Some(0), None]);
+
+    assert_eq!(scheduler.alarms[0].last_run, last_run);
+    assert!(scheduler.alarms[1].last_run > last_run);
+}
+
+#[test]
+#[cfg(unix)]
+fn touching_the_target_of_a_linked_configuration_keeps_the_last_runs() {
+
+    let dir = TempDir::new("linked_config");
+    let target = dir.write("dotfiles/rustine/config", "MTWTF__;06:30:00;/a.ogg;75\n");
+    let link = dir.join("rustine/config");
+    ::std::fs::create_dir_all(dir.join("rustine")).unwrap();
+    ::std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let directory = paths::directory_of(&link);
+    let watched_files = paths::configuration_files(&link, &directory);
+    let alarms = load_configuration(link.to_str().unwrap()).0;
+    let mut scheduler = Scheduler::new(alarms.clone());
+    let last_run = Local::now() - Duration::days(1);
+    scheduler.alarms[0].last_run = last_run;
+
+    // the editor of the dotfiles repository writes the same alarms again
+    dir.write("dotfiles/rustine/config", "MTWTF__;06:30:00;/a.ogg;75\n");
+    let event = DebouncedEvent::Write(target.canonicalize().unwrap());
+    assert!(paths::concerns_configuration(&event, &watched_files, &directory.join("conf.d")));
+
+    let (reloaded, diagnostics) = load_configuration(link.to_str().unwrap());
+    assert!(diagnostics.is_empty());
+    assert!(same_configuration(&alarms, &reloaded));
+    scheduler.reconfigure(reloaded);
+    assert_eq!(scheduler.alarms[0].last_run, last_run);
+}
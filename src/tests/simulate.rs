@@ -0,0 +1,37 @@
+use chrono::prelude::*;
+use config::{AlarmConfig, Time};
+use simulate::simulate;
+use zone::Zone;
+
+#[test]
+fn simulate_explains_daylight_saving_time_transitions() {
+
+    let paris = chrono_tz::Europe::Paris;
+    let from: DateTime<Local> = paris.with_ymd_and_hms(2026, 3, 28, 0, 0, 0).unwrap().with_timezone(&Local);
+    let to: DateTime<Local> = paris.with_ymd_and_hms(2026, 3, 31, 0, 0, 0).unwrap().with_timezone(&Local);
+
+    let night = AlarmConfig {
+        days: vec![true; 7],
+        time: Time { hours: 2, minutes: 30, seconds: 0 },
+        timezone: Zone::Named(paris),
+        ..AlarmConfig::default()
+    };
+    let (_, events) = simulate(vec![night.clone()], &from, &to);
+
+    // clocks go forward at 2:00 on sunday 29 march
+    let times: Vec<DateTime<Local>> = events.iter().map(|event| event.time).collect();
+    assert_eq!(times, vec![
+        paris.with_ymd_and_hms(2026, 3, 28, 2, 30, 0).unwrap().with_timezone(&Local),
+        paris.with_ymd_and_hms(2026, 3, 29, 3, 0, 0).unwrap().with_timezone(&Local),
+        paris.with_ymd_and_hms(2026, 3, 30, 2, 30, 0).unwrap().with_timezone(&Local)
+    ]);
+    assert!(events[0].notes.iter().all(|note| !note.contains("clocks")));
+    assert!(events[1].notes.iter().any(|note| note.starts_with("clocks go forward, 02:30:00 is skipped")));
+
+    // clocks go back at 3:00 on sunday 25 october
+    let from: DateTime<Local> = paris.with_ymd_and_hms(2026, 10, 25, 0, 0, 0).unwrap().with_timezone(&Local);
+    let (_, events) = simulate(vec![night], &from, &(from + chrono::Duration::days(1)));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].time, Utc.with_ymd_and_hms(2026, 10, 25, 0, 30, 0).unwrap().with_timezone(&Local));
+    assert!(events[0].notes.iter().any(|note| note.starts_with("clocks go back")));
+}
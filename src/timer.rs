@@ -0,0 +1,212 @@
+use chrono::prelude::*;
+use chrono::Duration;
+use init::SAMPLE_AUDIO;
+use paths;
+use runner::Sound;
+use std::path::Path;
+
+/// One-off alarm ringing after a duration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timer {
+    /// Number used to refer to the timer, starting at 1
+    pub id: u32,
+    pub label: Option<String>,
+    pub audio_file: String,
+    /// Time at which the timer rings
+    pub end: DateTime<Local>
+}
+
+impl Timer {
+
+    /// Name of the timer in messages, its number followed by its label
+    pub fn name(&self) -> String {
+        match self.label {
+            Some(ref label) => format!("{} ({})", self.id, label),
+            None => self.id.to_string()
+        }
+    }
+
+    /// Sound played when the timer rings, at full volume
+    pub fn sound(&self) -> Sound {
+        Sound { audio_file: self.audio_file.clone(), volume: 100 }
+    }
+}
+
+/// Parses a duration such as `25m`, `90s`, `1h30m` or `1h30m15s`, a number alone being minutes
+/// Durations too long to be represented are invalid
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid duration, expected such as 25m, 90s or 1h30m: {}", s);
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+        return s.parse().ok()
+            .and_then(Duration::try_minutes)
+            .filter(|duration| *duration > Duration::zero())
+            .ok_or_else(invalid);
+    }
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in s.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' if !number.is_empty() => {
+                let value: i64 = number.parse().map_err(|_| invalid())?;
+                let duration = match c {
+                    'h' => Duration::try_hours(value),
+                    'm' => Duration::try_minutes(value),
+                    _ => Duration::try_seconds(value)
+                };
+                total = duration.and_then(|duration| total.checked_add(&duration)).ok_or_else(invalid)?;
+                number.clear();
+            },
+            _ => return Err(invalid())
+        }
+    }
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("25m"), Ok(Duration::minutes(25)));
+    assert_eq!(parse_duration("25"), Ok(Duration::minutes(25)));
+    assert_eq!(parse_duration("90s"), Ok(Duration::seconds(90)));
+    assert_eq!(parse_duration("1h30m15s"), Ok(Duration::seconds(5415)));
+    assert!(parse_duration("0m").is_err());
+    assert!(parse_duration("0").is_err());
+    assert!(parse_duration("9999999999999h").is_err());
+    assert!(parse_duration("99999999999999999").is_err());
+    assert!(parse_duration("99999999999999999999s").is_err());
+    assert!(parse_duration("1h30").is_err());
+    assert!(parse_duration("m").is_err());
+    assert!(parse_duration("soon").is_err());
+    assert!(parse_duration("").is_err());
+}
+
+/// Checks if the last word of the timer command is an audio file rather than a word of the label:
+/// a file with a known audio extension, or an existing file, relative paths being relative to `base`
+fn is_audio_file(word: &str, base: &Path) -> bool {
+    [".wav", ".ogg", ".flac", ".mp3"].iter().any(|extension| word.to_lowercase().ends_with(extension))
+        || paths::expand(word, base).is_ok_and(|path| Path::new(&path).is_file())
+}
+
+/// Reads the `<duration> [label] [audio]` argument of the timer command
+/// The audio file is the last word when it is an audio file, relative paths being relative to `base`
+pub fn parse_timer(argument: &str, base: &Path) -> Result<(Duration, Option<String>, Option<String>), String> {
+    let mut words: Vec<&str> = argument.split_whitespace().collect();
+    if words.is_empty() {
+        return Err("Missing duration".to_string());
+    }
+    let duration = parse_duration(words.remove(0))?;
+
+    let audio = match words.last() {
+        Some(word) if is_audio_file(word, base) => Some(paths::expand(word, base)?),
+        _ => None
+    };
+    if audio.is_some() {
+        words.pop();
+    }
+    let label = if words.is_empty() { None } else { Some(words.join(" ")) };
+    Ok((duration, label, audio))
+}
+
+#[test]
+fn test_parse_timer() {
+    let base = Path::new("/etc/rustine");
+    assert_eq!(parse_timer("25m", base), Ok((Duration::minutes(25), None, None)));
+    assert_eq!(parse_timer("3m Soft boiled eggs", base), Ok((Duration::minutes(3), Some("Soft boiled eggs".to_string()), None)));
+    assert_eq!(parse_timer("1h Stand-up sounds/bell.ogg", base),
+        Ok((Duration::hours(1), Some("Stand-up".to_string()), Some("/etc/rustine/sounds/bell.ogg".to_string()))));
+    assert_eq!(parse_timer("10s /tmp/ding.wav", base), Ok((Duration::seconds(10), None, Some("/tmp/ding.wav".to_string()))));
+    // words with a slash that are not audio files stay in the label
+    assert_eq!(parse_timer("10m 1/2 dose", base), Ok((Duration::minutes(10), Some("1/2 dose".to_string()), None)));
+    assert_eq!(parse_timer("10m dose 1/2", base), Ok((Duration::minutes(10), Some("dose 1/2".to_string()), None)));
+    assert!(parse_timer("Tea 3m", base).is_err());
+    assert!(parse_timer("", base).is_err());
+}
+
+/// Audio file of the timers started without one: the sample audio file of the configuration directory `base`
+pub fn default_audio(base: &Path) -> String {
+    base.join(SAMPLE_AUDIO).to_string_lossy().into_owned()
+}
+
+/// Remaining time written `MM:SS`, or `H:MM:SS` from one hour
+pub fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.num_seconds().max(0);
+    match seconds / 3600 {
+        0 => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, (seconds % 3600) / 60, seconds % 60)
+    }
+}
+
+#[test]
+fn test_format_remaining() {
+    assert_eq!(format_remaining(Duration::seconds(1452)), "24:12");
+    assert_eq!(format_remaining(Duration::seconds(5415)), "1:30:15");
+    assert_eq!(format_remaining(Duration::seconds(-3)), "00:00");
+}
+
+/// Time at which something lasting a duration from `now` ends, an error when it is too far away
+pub fn end_after(now: &DateTime<Local>, duration: Duration) -> Result<DateTime<Local>, String> {
+    now.checked_add_signed(duration).ok_or(format!("Duration too long: {}", format_remaining(duration)))
+}
+
+#[test]
+fn test_end_after() {
+    let now = Local::now();
+    assert_eq!(end_after(&now, Duration::minutes(25)), Ok(now + Duration::minutes(25)));
+    assert!(end_after(&now, parse_duration("9999999999h").unwrap()).is_err());
+}
+
+/// Running timers
+#[derive(Debug, Default)]
+pub struct Timers {
+    pub timers: Vec<Timer>,
+    /// Number of the last timer started
+    last_id: u32
+}
+
+impl Timers {
+
+    /// Starts a timer ringing after the given duration
+    pub fn start(&mut self, duration: Duration, label: Option<String>, audio_file: String, now: &DateTime<Local>) -> Result<&Timer, String> {
+        let end = end_after(now, duration)?;
+        self.last_id += 1;
+        self.timers.push(Timer { id: self.last_id, label, audio_file, end });
+        Ok(&self.timers[self.timers.len() - 1])
+    }
+
+    /// Drops a timer given by number or label, case insensitive
+    pub fn cancel(&mut self, id: &str) -> Option<Timer> {
+        let id_lowercase = id.to_lowercase();
+        let position = self.timers.iter()
+            .position(|timer| timer.id.to_string() == id || timer.label.as_ref().is_some_and(|label| label.to_lowercase() == id_lowercase))?;
+        Some(self.timers.remove(position))
+    }
+
+    /// Removes and returns the timers that end at or before the given time
+    pub fn due(&mut self, now: &DateTime<Local>) -> Vec<Timer> {
+        let (due, running) = self.timers.drain(..).partition(|timer| timer.end <= *now);
+        self.timers = running;
+        due
+    }
+}
+
+#[test]
+fn test_timers() {
+    let now = Local::now();
+    let mut timers = Timers::default();
+
+    assert_eq!(default_audio(Path::new("/etc/rustine")), "/etc/rustine/sounds/chime.wav");
+    assert_eq!(timers.start(Duration::minutes(25), None, "/a.ogg".to_string(), &now).unwrap().end, now + Duration::minutes(25));
+    timers.start(Duration::minutes(3), Some("Tea".to_string()), "/a.ogg".to_string(), &now).unwrap();
+    timers.start(Duration::minutes(5), None, "/a.ogg".to_string(), &now).unwrap();
+    assert!(timers.start(parse_duration("9999999999h").unwrap(), None, "/a.ogg".to_string(), &now).is_err());
+
+    assert_eq!(timers.cancel("tea").map(|timer| timer.id), Some(2));
+    assert!(timers.cancel("2").is_none());
+    assert_eq!(timers.due(&(now + Duration::minutes(10))).iter().map(|timer| timer.id).collect::<Vec<_>>(), vec![3]);
+    assert_eq!(timers.timers.len(), 1);
+    assert_eq!(timers.start(Duration::minutes(1), None, "/a.ogg".to_string(), &now).unwrap().id, 4);
+}
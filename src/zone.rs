@@ -0,0 +1,95 @@
+use chrono::prelude::*;
+use chrono::{Duration, LocalResult, NaiveDateTime};
+use chrono_tz::Tz;
+use std::fmt;
+
+/// Time zone in which an alarm is evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Zone {
+    /// System time zone, read again at each use so that a change is taken into account
+    #[default]
+    Local,
+    /// IANA time zone such as `Europe/Paris`
+    Named(Tz)
+}
+
+impl Zone {
+
+    /// Parses an IANA time zone name, `local` for the system time zone
+    pub fn parse(name: &str) -> Result<Zone, String> {
+        if name.eq_ignore_ascii_case("local") {
+            Ok(Zone::Local)
+        } else {
+            name.parse::<Tz>().map(Zone::Named).map_err(|_| format!("Unknown time zone: {}", name))
+        }
+    }
+
+    /// Date and time of an instant in this time zone
+    pub fn naive<T: TimeZone>(&self, instant: &DateTime<T>) -> NaiveDateTime {
+        match *self {
+            Zone::Local => instant.with_timezone(&Local).naive_local(),
+            Zone::Named(tz) => instant.with_timezone(&tz).naive_local()
+        }
+    }
+
+    /// Instants matching a date and time of this time zone
+    pub fn from_local(&self, naive: &NaiveDateTime) -> LocalResult<DateTime<Local>> {
+        match *self {
+            Zone::Local => Local.from_local_datetime(naive),
+            Zone::Named(tz) => tz.from_local_datetime(naive).map(|dt| dt.with_timezone(&Local))
+        }
+    }
+
+    /// Instant at which a date and time of this time zone occurs, around daylight saving time transitions:
+    /// - a time skipped when clocks go forward occurs at the end of the gap
+    /// - a time repeated when clocks go back occurs the first time only
+    pub fn resolve(&self, naive: &NaiveDateTime) -> Option<DateTime<Local>> {
+        if let Some(instant) = self.from_local(naive).earliest() {
+            return Some(instant);
+        }
+
+        // gaps start and end on a minute, look for the first minute after the gap
+        let minute = naive.with_second(0)?.with_nanosecond(0)?;
+        (1..=24 * 60)
+            .filter_map(|m| self.from_local(&(minute + Duration::minutes(m))).earliest())
+            .next()
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Zone::Local => write!(f, "local"),
+            Zone::Named(tz) => write!(f, "{}", tz.name())
+        }
+    }
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(Zone::parse("local"), Ok(Zone::Local));
+    assert_eq!(Zone::parse("Europe/Paris"), Ok(Zone::Named(chrono_tz::Europe::Paris)));
+    assert!(Zone::parse("Mars/Olympus_Mons").is_err());
+}
+
+#[test]
+fn test_naive() {
+    let instant = Utc.with_ymd_and_hms(2026, 10, 20, 6, 0, 0).unwrap();
+    let paris = Zone::Named(chrono_tz::Europe::Paris);
+    let new_york = Zone::Named(chrono_tz::America::New_York);
+    assert_eq!(paris.naive(&instant), NaiveDate::from_ymd_opt(2026, 10, 20).unwrap().and_hms_opt(8, 0, 0).unwrap());
+    assert_eq!(new_york.naive(&instant), NaiveDate::from_ymd_opt(2026, 10, 20).unwrap().and_hms_opt(2, 0, 0).unwrap());
+}
+
+#[test]
+fn test_resolve() {
+    let paris = Zone::Named(chrono_tz::Europe::Paris);
+    let at = |m: u32, d: u32, h: u32, min: u32| NaiveDate::from_ymd_opt(2026, m, d).unwrap().and_hms_opt(h, min, 0).unwrap();
+
+    // regular day
+    assert_eq!(paris.resolve(&at(10, 20, 7, 0)), Some(Utc.with_ymd_and_hms(2026, 10, 20, 5, 0, 0).unwrap().with_timezone(&Local)));
+    // 02:30 does not exist on 29 March, clocks go from 02:00 to 03:00 (01:00 UTC)
+    assert_eq!(paris.resolve(&at(3, 29, 2, 30)), Some(Utc.with_ymd_and_hms(2026, 3, 29, 1, 0, 0).unwrap().with_timezone(&Local)));
+    // 02:30 happens twice on 25 October, at 00:30 and 01:30 UTC
+    assert_eq!(paris.resolve(&at(10, 25, 2, 30)), Some(Utc.with_ymd_and_hms(2026, 10, 25, 0, 30, 0).unwrap().with_timezone(&Local)));
+}